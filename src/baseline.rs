@@ -0,0 +1,146 @@
+use std::collections::HashSet;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::detect::{Detector, Finding};
+
+/// Accepted findings, keyed by their fingerprint. The other fields only help reviewing the file.
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct Baseline {
+    pub findings: Vec<BaselineEntry>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct BaselineEntry {
+    pub fingerprint: String,
+    pub rule_id: String,
+    pub project_path: String,
+    pub job_id: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub comment: Option<String>,
+}
+
+impl Baseline {
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read baseline {}: {}", path.display(), e))?;
+
+        serde_json::from_str(&content)
+            .map_err(|e| format!("Invalid baseline {}: {}", path.display(), e))
+    }
+
+    /// Loads the baseline, a missing file is an empty baseline when it is about to be created.
+    pub fn load_or_default(path: &Path, create: bool) -> Result<Self, String> {
+        if create && !path.exists() {
+            Ok(Baseline::default())
+        } else {
+            Baseline::load(path)
+        }
+    }
+
+    pub fn write(&self, path: &Path) -> Result<(), String> {
+        let content = serde_json::to_string_pretty(self)
+            .map_err(|e| format!("Failed to serialize baseline: {}", e))?;
+
+        std::fs::write(path, content)
+            .map_err(|e| format!("Failed to write baseline {}: {}", path.display(), e))
+    }
+
+    pub fn fingerprints(&self) -> HashSet<&str> {
+        self.findings
+            .iter()
+            .map(|e| e.fingerprint.as_str())
+            .collect()
+    }
+
+    /// Adds the findings not in the baseline yet, returns how many were added.
+    pub fn add(&mut self, findings: &[Finding]) -> usize {
+        let mut known: HashSet<String> = self
+            .findings
+            .iter()
+            .map(|e| e.fingerprint.clone())
+            .collect();
+        let before = self.findings.len();
+
+        for finding in findings {
+            if known.insert(finding.fingerprint.clone()) {
+                self.findings.push(BaselineEntry {
+                    fingerprint: finding.fingerprint.clone(),
+                    rule_id: finding.rule_id.clone(),
+                    project_path: finding.project_path.clone(),
+                    job_id: finding.job_id,
+                    comment: None,
+                });
+            }
+        }
+
+        self.findings.len() - before
+    }
+}
+
+/// Drops the findings in the baseline or matching an allowlist, returns the kept findings and
+/// the number of suppressed ones.
+pub fn suppress(
+    findings: Vec<Finding>,
+    detector: &Detector,
+    baseline: Option<&Baseline>,
+) -> (Vec<Finding>, usize) {
+    let fingerprints = baseline.map(|b| b.fingerprints()).unwrap_or_default();
+    let total = findings.len();
+
+    let kept: Vec<Finding> = findings
+        .into_iter()
+        .filter(|f| !fingerprints.contains(f.fingerprint.as_str()) && !detector.is_allowed(f))
+        .collect();
+    let suppressed = total - kept.len();
+
+    (kept, suppressed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::detect::{default_rules, test_finding};
+
+    #[test]
+    fn test_baseline_suppresses_known_findings() {
+        let mut baseline = Baseline::default();
+        assert_eq!(baseline.add(&[test_finding(1, 10, 1)]), 1);
+        assert_eq!(
+            baseline.add(&[test_finding(1, 10, 1), test_finding(1, 10, 2)]),
+            1
+        );
+
+        let findings = vec![
+            test_finding(1, 10, 1),
+            test_finding(1, 10, 2),
+            test_finding(1, 11, 1),
+        ];
+        let (kept, suppressed) =
+            suppress(findings, &Detector::new(default_rules()), Some(&baseline));
+
+        assert_eq!(suppressed, 2);
+        assert_eq!(kept.len(), 1);
+        assert_eq!(kept[0].job_id, 11);
+    }
+
+    #[test]
+    fn test_baseline_round_trip() {
+        let path = std::env::temp_dir().join(format!(
+            "gitlab_auditor_baseline_{}.json",
+            std::process::id()
+        ));
+        assert!(Baseline::load(&path).is_err());
+
+        let mut baseline = Baseline::load_or_default(&path, true).unwrap();
+        baseline.add(&[test_finding(1, 10, 1)]);
+        baseline.write(&path).unwrap();
+
+        let loaded = Baseline::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(loaded.findings.len(), 1);
+        assert_eq!(loaded.findings[0].job_id, 10);
+    }
+}
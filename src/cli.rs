@@ -1,8 +1,27 @@
-use clap::Parser;
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use clap::{Parser, Subcommand};
 use colored::Colorize;
 use url::Url;
 
-const GITLAB_API_PATH: &str = "/api/v4";
+use crate::crypto::parse_recipients;
+use crate::detect::Severity;
+use crate::http::HttpOptions;
+use crate::issues::IssueOptions;
+use crate::notify::{Sink, SinkKind, parse_sinks};
+use crate::output::DEFAULT_OUTPUT_DIR;
+use crate::policy::FailPolicy;
+use crate::report::ReportFormat;
+use crate::report::gitlab::SECRET_DETECTION_REPORT_FILE;
+use crate::scans::filter::{Glob, JobFilter, JobStatus, parse_time};
+use crate::scans::shared::api_url;
+use crate::scans::watch::WATCH_STATE_FILE;
+use crate::verify::{DEFAULT_VERIFY_RATE, Provider, parse_verifier_urls};
+
+const PROXY_SCHEMES: [&str; 4] = ["http", "https", "socks5", "socks5h"];
 
 pub fn print_banner() {
     println!();
@@ -52,24 +71,284 @@ pub enum ScanType {
     Project(i32),
 }
 
-#[derive(Parser, Debug)]
+#[derive(Subcommand, Debug, Clone)]
+pub enum Command {
+    /// Decrypt a trace, manifest or report written with --encrypt-to.
+    #[command(visible_alias = "view")]
+    Decrypt {
+        #[arg(
+            short = 'i',
+            long,
+            required = true,
+            value_name = "FILE",
+            help = "An age identity file holding the private key of one of the recipients."
+        )]
+        identity: PathBuf,
+
+        #[arg(
+            short = 'o',
+            long,
+            required = false,
+            value_name = "FILE",
+            help = "Write the plaintext to this file instead of printing it."
+        )]
+        output: Option<PathBuf>,
+
+        #[arg(value_name = "ENCRYPTED_FILE")]
+        file: PathBuf,
+    },
+
+    /// Compare two reports and show the new, still present and resolved findings.
+    Diff {
+        #[arg(
+            short = 'i',
+            long,
+            required = false,
+            value_name = "FILE",
+            help = "An age identity file, required when a report is encrypted."
+        )]
+        identity: Option<PathBuf>,
+
+        #[arg(
+            short = 'F',
+            long,
+            required = false,
+            value_enum,
+            default_value = "json",
+            help = "The format of the comparison."
+        )]
+        format: ReportFormat,
+
+        #[arg(
+            short = 'o',
+            long,
+            required = false,
+            value_name = "FILE",
+            help = "Write the comparison to this file instead of printing it, encrypted with --encrypt-to."
+        )]
+        output: Option<PathBuf>,
+
+        #[arg(value_name = "OLD_REPORT_OR_RUN_DIR")]
+        old: PathBuf,
+
+        #[arg(value_name = "NEW_REPORT_OR_RUN_DIR")]
+        new: PathBuf,
+    },
+
+    /// Re-run the detection over the traces of a previous run, without token nor network.
+    Analyze {
+        #[arg(
+            short = 'i',
+            long,
+            required = false,
+            value_name = "FILE",
+            help = "An age identity file, required when the run is encrypted."
+        )]
+        identity: Option<PathBuf>,
+
+        #[arg(value_name = "RUN_DIR")]
+        run_dir: PathBuf,
+    },
+
+    /// Receive Gitlab job events and scan each finished job as soon as it completes.
+    Serve {
+        #[arg(
+            short = 'l',
+            long,
+            required = false,
+            default_value = "0.0.0.0:8080",
+            value_name = "ADDRESS",
+            help = "The address the webhook receiver listens on."
+        )]
+        listen: SocketAddr,
+
+        #[arg(
+            long = "secret-token",
+            required = false,
+            value_name = "TOKEN",
+            help = "The secret token configured on the Gitlab webhook, GITLAB_AUDITOR_WEBHOOK_SECRET by default."
+        )]
+        secret_token: Option<String>,
+
+        #[arg(
+            long = "queue-size",
+            required = false,
+            default_value_t = 100,
+            value_name = "JOBS",
+            help = "How many jobs can wait to be scanned, further events are refused with a 503."
+        )]
+        queue_size: usize,
+    },
+
+    /// Scan the earlier jobs of the running Gitlab CI pipeline and write a secret detection report.
+    Ci {
+        #[arg(
+            short = 'o',
+            long,
+            required = false,
+            default_value = SECRET_DETECTION_REPORT_FILE,
+            value_name = "FILE",
+            help = "Where to write the report, to upload as artifacts:reports:secret_detection."
+        )]
+        output: PathBuf,
+    },
+
+    /// Query the database filled by --db, with canned filters or raw SQL.
+    Query {
+        #[arg(
+            short = 'd',
+            long,
+            required = true,
+            value_name = "FILE",
+            help = "The SQLite database written by scans run with --db."
+        )]
+        db: PathBuf,
+
+        #[arg(
+            long,
+            required = false,
+            value_name = "PATH",
+            help = "Only findings of this project, or of the projects of this namespace."
+        )]
+        project: Option<String>,
+
+        #[arg(
+            long,
+            required = false,
+            value_name = "RULE_ID",
+            help = "Only findings of this rule."
+        )]
+        rule: Option<String>,
+
+        #[arg(
+            long,
+            required = false,
+            value_enum,
+            value_name = "SEVERITY",
+            help = "Only findings at or above this severity."
+        )]
+        severity: Option<Severity>,
+
+        #[arg(
+            long,
+            required = false,
+            value_name = "DATE",
+            help = "Only findings of jobs created on or after this date (2025-01-31)."
+        )]
+        since: Option<String>,
+
+        #[arg(
+            long,
+            required = false,
+            value_name = "DATE",
+            help = "Only findings of jobs created on or before this date (2025-01-31)."
+        )]
+        until: Option<String>,
+
+        #[arg(
+            long,
+            required = false,
+            value_name = "QUERY",
+            conflicts_with_all = ["project", "rule", "severity", "since", "until"],
+            help = "Run this SQL query instead, the database is opened read-only."
+        )]
+        sql: Option<String>,
+    },
+
+    /// Remediate the findings of a previous report through the Gitlab API.
+    Remediate {
+        #[command(subcommand)]
+        action: RemediateAction,
+    },
+}
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum RemediateAction {
+    /// Erase the logs of every job with findings (POST /projects/:id/jobs/:job_id/erase).
+    Erase {
+        #[arg(
+            short = 'r',
+            long,
+            required = true,
+            value_name = "FILE_OR_RUN_DIR",
+            help = "A report.json file, or the run directory holding it."
+        )]
+        report: PathBuf,
+
+        #[arg(
+            short = 'i',
+            long,
+            required = false,
+            value_name = "FILE",
+            help = "An age identity file, required when the report is encrypted."
+        )]
+        identity: Option<PathBuf>,
+
+        #[arg(
+            long,
+            required = false,
+            help = "Only show the plan, nothing is erased."
+        )]
+        dry_run: bool,
+
+        #[arg(
+            short = 'y',
+            long,
+            required = false,
+            help = "Erase without asking for confirmation."
+        )]
+        yes: bool,
+
+        #[arg(
+            long,
+            required = false,
+            value_name = "FILE",
+            default_value = "remediation-audit.jsonl",
+            help = "File where every remediation action is appended as a JSON line."
+        )]
+        audit_log: PathBuf,
+    },
+}
+
+#[derive(Parser, Debug, Default)]
 #[command(version, about, long_about = None)]
 pub struct Args {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    #[arg(
+        short = 'c',
+        long,
+        required = false,
+        value_name = "FILE",
+        help = "Path to a TOML configuration file, defaults to $XDG_CONFIG_HOME/gitlab_auditor/config.toml."
+    )]
+    pub config: Option<PathBuf>,
+
+    #[arg(
+        short = 'P',
+        long,
+        required = false,
+        value_name = "NAME",
+        help = "The configuration profile to use, defaults to the 'default_profile' of the configuration file."
+    )]
+    pub profile: Option<String>,
+
     #[arg(
         short = 't',
         long,
-        required = true,
+        required = false,
         help = "Your Gitlab Personal Access Token, can be created in your Gitlab account settings."
     )]
-    pub gitlab_token: String,
+    pub gitlab_token: Option<String>,
 
     #[arg(
         short = 'u',
         long,
-        required = true,
+        required = false,
         help = "The URL of the Gitlab instance to be scanned, e.g. https://gitlab.com."
     )]
-    pub instance_url: String,
+    pub instance_url: Option<String>,
 
     #[arg(
         short = 'f',
@@ -96,43 +375,403 @@ pub struct Args {
         help = "The id of the project to be scanned, e.g. 10997."
     )]
     pub project_scan: Option<i32>,
+
+    #[arg(
+        long,
+        required = false,
+        value_name = "DATE",
+        help = "Only scan the jobs finished on or after this date (YYYY-MM-DD or RFC 3339)."
+    )]
+    pub since: Option<String>,
+
+    #[arg(
+        long,
+        required = false,
+        value_name = "DATE",
+        help = "Only scan the jobs finished on or before this date (YYYY-MM-DD or RFC 3339)."
+    )]
+    pub until: Option<String>,
+
+    #[arg(
+        long,
+        required = false,
+        value_enum,
+        value_delimiter = ',',
+        value_name = "STATUS",
+        help = "Only scan the jobs with these statuses, all finished ones by default (success, failed, canceled)."
+    )]
+    pub status: Vec<JobStatus>,
+
+    #[arg(
+        long = "ref",
+        required = false,
+        value_name = "GLOB",
+        help = "Only scan the jobs of the matching branches or tags, e.g. 'release/*'."
+    )]
+    pub git_ref: Option<String>,
+
+    #[arg(
+        long = "job-name",
+        required = false,
+        value_name = "REGEX",
+        help = "Only scan the jobs whose name matches this regular expression."
+    )]
+    pub job_name: Option<String>,
+
+    #[arg(
+        long = "protected-refs-only",
+        required = false,
+        overrides_with = "no_protected_refs_only",
+        help = "Only scan the jobs of protected branches and tags."
+    )]
+    pub protected_refs_only: bool,
+
+    #[arg(
+        long = "no-protected-refs-only",
+        required = false,
+        overrides_with = "protected_refs_only",
+        help = "Scan the jobs of every ref, even when the profile sets --protected-refs-only."
+    )]
+    pub no_protected_refs_only: bool,
+
+    #[arg(
+        long = "ca-cert",
+        required = false,
+        value_name = "FILE",
+        help = "A PEM bundle of additional CA certificates to trust, can be repeated."
+    )]
+    pub ca_cert: Vec<PathBuf>,
+
+    #[arg(
+        long,
+        required = false,
+        value_name = "FILE",
+        requires = "client_key",
+        help = "A PEM client certificate for mutual TLS, used with --client-key."
+    )]
+    pub client_cert: Option<PathBuf>,
+
+    #[arg(
+        long,
+        required = false,
+        value_name = "FILE",
+        requires = "client_cert",
+        help = "The PEM (PKCS#8) private key of the client certificate."
+    )]
+    pub client_key: Option<PathBuf>,
+
+    #[arg(
+        long,
+        required = false,
+        value_name = "URL",
+        help = "A proxy for every request, e.g. http://proxy:3128 or socks5h://proxy:1080. Defaults to the HTTPS_PROXY/ALL_PROXY environment variables."
+    )]
+    pub proxy: Option<String>,
+
+    #[arg(
+        long,
+        required = false,
+        value_name = "HOSTS",
+        help = "Comma-separated hosts that bypass the proxy, defaults to the NO_PROXY environment variable."
+    )]
+    pub no_proxy: Option<String>,
+
+    #[arg(
+        long,
+        required = false,
+        overrides_with = "no_insecure",
+        help = "Disable TLS certificate verification. Only use it to troubleshoot, the connection can be intercepted."
+    )]
+    pub insecure: bool,
+
+    #[arg(
+        long = "no-insecure",
+        required = false,
+        overrides_with = "insecure",
+        help = "Verify TLS certificates, even when the profile sets --insecure."
+    )]
+    pub no_insecure: bool,
+
+    #[arg(
+        short = 'o',
+        long,
+        required = false,
+        value_name = "DIR",
+        help = "Directory where each run creates a timestamped subdirectory, defaults to ./results."
+    )]
+    pub output: Option<PathBuf>,
+
+    #[arg(
+        long,
+        required = false,
+        help = "Remove the run directories of earlier scans from the output directory before scanning."
+    )]
+    pub clean: bool,
+
+    #[arg(
+        long = "encrypt-to",
+        required = false,
+        value_name = "RECIPIENT",
+        help = "Encrypt every trace and report to this age public key (age1...), can be repeated."
+    )]
+    pub encrypt_to: Vec<String>,
+
+    #[arg(
+        long,
+        required = false,
+        value_name = "FILE",
+        help = "A TOML rule pack adding or replacing detection rules, can be repeated."
+    )]
+    pub rules: Vec<PathBuf>,
+
+    #[arg(
+        long,
+        required = false,
+        overrides_with = "no_redact",
+        help = "Write traces with every detected secret replaced by a [REDACTED:<rule>] placeholder, plus a sidecar locating each one."
+    )]
+    pub redact: bool,
+
+    #[arg(
+        long = "no-redact",
+        required = false,
+        overrides_with = "redact",
+        help = "Write the traces as fetched, even when the profile sets --redact."
+    )]
+    pub no_redact: bool,
+
+    #[arg(
+        long = "create-issues",
+        required = false,
+        overrides_with = "no_create_issues",
+        help = "Open or update one confidential issue listing the redacted findings in every affected project."
+    )]
+    pub create_issues: bool,
+
+    #[arg(
+        long = "no-create-issues",
+        required = false,
+        overrides_with = "create_issues",
+        help = "Do not open issues, even when the profile sets --create-issues."
+    )]
+    pub no_create_issues: bool,
+
+    #[arg(
+        long = "issue-label",
+        required = false,
+        value_name = "LABEL",
+        help = "A label set on the issues opened by --create-issues, can be repeated."
+    )]
+    pub issue_label: Vec<String>,
+
+    #[arg(
+        long = "issue-assignee",
+        required = false,
+        value_name = "USERNAME",
+        help = "A user assigned to the issues opened by --create-issues, can be repeated."
+    )]
+    pub issue_assignee: Vec<String>,
+
+    #[arg(
+        long = "verify-gitlab-tokens",
+        required = false,
+        overrides_with = "no_verify_gitlab_tokens",
+        help = "Check whether the leaked Gitlab tokens are still live on the scanned instance, live ones are reported as critical."
+    )]
+    pub verify_gitlab_tokens: bool,
+
+    #[arg(
+        long = "no-verify-gitlab-tokens",
+        required = false,
+        overrides_with = "verify_gitlab_tokens",
+        help = "Do not check Gitlab tokens, even when the profile sets --verify-gitlab-tokens."
+    )]
+    pub no_verify_gitlab_tokens: bool,
+
+    #[arg(
+        long = "revoke-live-tokens",
+        required = false,
+        overrides_with = "no_revoke_live_tokens",
+        help = "Revoke the leaked Gitlab tokens found live, requires an administrator token. Implies --verify-gitlab-tokens."
+    )]
+    pub revoke_live_tokens: bool,
+
+    #[arg(
+        long = "no-revoke-live-tokens",
+        required = false,
+        overrides_with = "revoke_live_tokens",
+        help = "Do not revoke live tokens, even when the profile sets --revoke-live-tokens."
+    )]
+    pub no_revoke_live_tokens: bool,
+
+    #[arg(
+        long,
+        required = false,
+        value_enum,
+        value_delimiter = ',',
+        value_name = "PROVIDER",
+        help = "Check whether the detected credentials of these providers are still live (gitlab, aws, slack, npm, docker)."
+    )]
+    pub verify: Vec<Provider>,
+
+    #[arg(
+        long = "verifier-url",
+        required = false,
+        value_name = "PROVIDER=URL",
+        help = "Send the verification requests of a provider to another endpoint, such as a mirror. Can be repeated."
+    )]
+    pub verifier_url: Vec<String>,
+
+    #[arg(
+        long = "verify-rate",
+        required = false,
+        value_name = "REQUESTS",
+        help = "Maximum number of verification requests per second, 5 by default."
+    )]
+    pub verify_rate: Option<u32>,
+
+    #[arg(
+        long = "notify-slack",
+        required = false,
+        value_name = "URL",
+        help = "Post a summary of the findings to this Slack incoming webhook, can be repeated."
+    )]
+    pub notify_slack: Vec<String>,
+
+    #[arg(
+        long = "notify-teams",
+        required = false,
+        value_name = "URL",
+        help = "Post a summary of the findings to this Microsoft Teams webhook, can be repeated."
+    )]
+    pub notify_teams: Vec<String>,
+
+    #[arg(
+        long = "notify-webhook",
+        required = false,
+        value_name = "URL",
+        help = "Post the findings as generic JSON to this webhook, can be repeated."
+    )]
+    pub notify_webhook: Vec<String>,
+
+    #[arg(
+        long = "notify-min-severity",
+        required = false,
+        value_enum,
+        value_name = "SEVERITY",
+        help = "Only notify about findings at or above this severity, low by default."
+    )]
+    pub notify_min_severity: Option<Severity>,
+
+    #[arg(
+        long,
+        required = false,
+        value_name = "FILE",
+        help = "A JSON baseline of accepted finding fingerprints, the findings in it are counted but not reported."
+    )]
+    pub baseline: Option<PathBuf>,
+
+    #[arg(
+        long = "update-baseline",
+        required = false,
+        help = "Add the findings of this run to the --baseline file, creating it if needed."
+    )]
+    pub update_baseline: bool,
+
+    #[arg(
+        long,
+        required = false,
+        value_name = "FILE",
+        help = "Record the inventory, jobs and findings of the run in this SQLite database, see the query command."
+    )]
+    pub db: Option<PathBuf>,
+
+    #[arg(
+        long = "report-format",
+        required = false,
+        value_enum,
+        value_delimiter = ',',
+        value_name = "FORMAT",
+        help = "Also write the report in these formats next to report.json (junit, markdown)."
+    )]
+    pub report_format: Vec<ReportFormat>,
+
+    #[arg(
+        long = "fail-on",
+        required = false,
+        value_enum,
+        value_name = "SEVERITY",
+        help = "Exit with code 1 when a reported finding is at or above this severity."
+    )]
+    pub fail_on: Option<Severity>,
+
+    #[arg(
+        long = "max-findings",
+        required = false,
+        value_name = "COUNT",
+        help = "Exit with code 1 when more than COUNT findings are reported."
+    )]
+    pub max_findings: Option<usize>,
+
+    #[arg(
+        long,
+        required = false,
+        value_name = "MINUTES",
+        help = "Keep running and scan the jobs finished since the previous poll every MINUTES."
+    )]
+    pub watch: Option<u64>,
+
+    #[arg(
+        long = "watch-state",
+        required = false,
+        value_name = "FILE",
+        help = "Where --watch records the last scanned job of each project, <output>/watch-state.json by default."
+    )]
+    pub watch_state: Option<PathBuf>,
 }
 
 pub trait ArgsValidation {
     fn gitlab_token(&self) -> Result<&String, String>;
     fn instance_url(&self) -> Result<Url, String>;
     fn scan_type(&self) -> Result<ScanType, String>;
+    fn http_options(&self) -> Result<HttpOptions, String>;
+    fn encrypt_to(&self) -> Result<Vec<String>, String>;
+    fn verify(&self) -> Vec<Provider>;
+    fn baseline(&self) -> Result<Option<PathBuf>, String>;
+    fn watch(&self) -> Result<Option<Duration>, String>;
+    fn job_filter(&self) -> Result<JobFilter, String>;
+    fn fail_policy(&self) -> FailPolicy;
 }
 
 impl ArgsValidation for Args {
     fn gitlab_token(&self) -> Result<&String, String> {
-        if !self.gitlab_token.starts_with("glpat-") {
+        let gitlab_token = self.gitlab_token.as_ref().ok_or(
+            "A Gitlab token is required: use --gitlab-token or a configuration profile".to_string(),
+        )?;
+
+        if !gitlab_token.starts_with("glpat-") {
             return Err("Token must start with 'glpat-'".to_string());
         }
 
-        if self.gitlab_token.len() != 26 {
+        if gitlab_token.len() != 26 {
             return Err("Token must be 26 characters".to_string());
         }
 
-        Ok(&self.gitlab_token)
+        Ok(gitlab_token)
     }
 
     fn instance_url(&self) -> Result<Url, String> {
         self.instance_url
+            .as_ref()
+            .ok_or(
+                "An instance URL is required: use --instance-url or a configuration profile"
+                    .to_string(),
+            )?
             .parse::<Url>()
             .map_err(|e| format!("Invalid URL: {}", e))
             .and_then(|url| {
                 if url.scheme() == "http" || url.scheme() == "https" {
-                    let url = if url.path().ends_with('/') {
-                        let mut url = url.clone();
-                        let path = url.path().to_string();
-                        url.set_path(&path[..path.len()-1]);
-                        url
-                    } else {
-                        url
-                    };
-                    let url = url.join(GITLAB_API_PATH).map_err(|_| "The URL value does not follow an URL scheme and cannot be concatenated with the Gitlab API Path")?;
-                    Ok(url)
+                    api_url(&url).ok_or("The URL value does not follow an URL scheme and cannot be concatenated with the Gitlab API Path".to_string())
                 } else {
                     Err("The URL value does not follow an URL scheme".to_string())
                 }
@@ -179,6 +818,112 @@ impl ArgsValidation for Args {
             _ => Err("Only one scan type can be specified: --full-scan, --group-scan, or --project-scan".to_string()),
         }
     }
+
+    fn http_options(&self) -> Result<HttpOptions, String> {
+        if self.client_cert.is_some() != self.client_key.is_some() {
+            return Err("--client-cert and --client-key must be provided together".to_string());
+        }
+
+        if let Some(proxy) = &self.proxy {
+            let proxy_url = proxy
+                .parse::<Url>()
+                .map_err(|e| format!("Invalid proxy URL: {}", e))?;
+
+            if !PROXY_SCHEMES.contains(&proxy_url.scheme()) {
+                return Err(format!(
+                    "Proxy scheme must be one of: {}",
+                    PROXY_SCHEMES.join(", ")
+                ));
+            }
+        }
+
+        Ok(HttpOptions {
+            ca_certs: self.ca_cert.clone(),
+            client_cert: self.client_cert.clone(),
+            client_key: self.client_key.clone(),
+            proxy: self.proxy.clone(),
+            no_proxy: self.no_proxy.clone(),
+            insecure: self.insecure,
+        })
+    }
+
+    fn encrypt_to(&self) -> Result<Vec<String>, String> {
+        parse_recipients(&self.encrypt_to)?;
+        Ok(self.encrypt_to.clone())
+    }
+
+    fn baseline(&self) -> Result<Option<PathBuf>, String> {
+        if self.update_baseline && self.baseline.is_none() {
+            return Err("--update-baseline requires a --baseline file".to_string());
+        }
+        Ok(self.baseline.clone())
+    }
+
+    fn watch(&self) -> Result<Option<Duration>, String> {
+        match self.watch {
+            Some(0) => Err("The --watch interval must be at least one minute".to_string()),
+            Some(minutes) => Ok(Some(Duration::from_secs(minutes * 60))),
+            None => Ok(None),
+        }
+    }
+
+    fn job_filter(&self) -> Result<JobFilter, String> {
+        let since = self
+            .since
+            .as_deref()
+            .map(|since| parse_time(since, false))
+            .transpose()?;
+        let until = self
+            .until
+            .as_deref()
+            .map(|until| parse_time(until, true))
+            .transpose()?;
+        if let (Some(since), Some(until)) = (since, until)
+            && since > until
+        {
+            return Err("--since must be before --until".to_string());
+        }
+
+        let job_name = self
+            .job_name
+            .as_deref()
+            .map(|pattern| {
+                regex::Regex::new(pattern)
+                    .map_err(|e| format!("Invalid --job-name regex '{}': {}", pattern, e))
+            })
+            .transpose()?;
+
+        Ok(JobFilter {
+            since,
+            until,
+            statuses: self.status.clone(),
+            git_ref: self.git_ref.as_deref().map(Glob::new).transpose()?,
+            job_name,
+            protected_refs_only: self.protected_refs_only,
+        })
+    }
+
+    fn fail_policy(&self) -> FailPolicy {
+        FailPolicy {
+            fail_on: self.fail_on,
+            max_findings: self.max_findings,
+        }
+    }
+
+    fn verify(&self) -> Vec<Provider> {
+        let mut providers = self.verify.clone();
+        if self.verify_gitlab_tokens || self.revoke_live_tokens {
+            providers.push(Provider::Gitlab);
+        }
+
+        let mut unique = Vec::new();
+        for provider in providers {
+            if !unique.contains(&provider) {
+                unique.push(provider);
+            }
+        }
+        unique
+    }
 }
 
 #[derive(Debug)]
@@ -186,17 +931,97 @@ pub struct ValidatedArgs {
     pub gitlab_token: String,
     pub instance_url: Url,
     pub scan_type: ScanType,
+    pub job_filter: JobFilter,
+    pub http: HttpOptions,
+    pub output_dir: PathBuf,
+    pub clean: bool,
+    pub encrypt_to: Vec<String>,
+    pub rules: Vec<PathBuf>,
+    pub redact: bool,
+    pub issues: Option<IssueOptions>,
+    pub verify: Vec<Provider>,
+    pub verifier_urls: HashMap<Provider, Url>,
+    pub verify_rate: u32,
+    pub revoke_live_tokens: bool,
+    pub notify: Vec<Sink>,
+    pub notify_min_severity: Severity,
+    pub baseline: Option<PathBuf>,
+    pub update_baseline: bool,
+    pub db: Option<PathBuf>,
+    pub report_formats: Vec<ReportFormat>,
+    pub watch: Option<Duration>,
+    pub watch_state: PathBuf,
+    pub fail_policy: FailPolicy,
 }
 
 pub fn validate_args(args: &Args) -> Result<ValidatedArgs, String> {
     let gitlab_token = args.gitlab_token()?.clone();
     let instance_url = args.instance_url()?;
     let scan_type = args.scan_type()?;
+    let job_filter = args.job_filter()?;
+    let http = args.http_options()?;
+    let encrypt_to = args.encrypt_to()?;
+    let verifier_urls = parse_verifier_urls(&args.verifier_url)?;
+    let baseline = args.baseline()?;
+    let watch = args.watch()?;
+    let output_dir = args
+        .output
+        .clone()
+        .unwrap_or_else(|| PathBuf::from(DEFAULT_OUTPUT_DIR));
+    let notify = [
+        parse_sinks(SinkKind::Slack, &args.notify_slack)?,
+        parse_sinks(SinkKind::Teams, &args.notify_teams)?,
+        parse_sinks(SinkKind::Generic, &args.notify_webhook)?,
+    ]
+    .concat();
 
     Ok(ValidatedArgs {
         gitlab_token,
         instance_url,
         scan_type,
+        job_filter,
+        http,
+        watch_state: args
+            .watch_state
+            .clone()
+            .unwrap_or_else(|| output_dir.join(WATCH_STATE_FILE)),
+        output_dir,
+        clean: args.clean,
+        encrypt_to,
+        rules: args.rules.clone(),
+        redact: args.redact,
+        issues: args.create_issues.then(|| IssueOptions {
+            labels: args.issue_label.clone(),
+            assignees: args.issue_assignee.clone(),
+        }),
+        verify: args.verify(),
+        verifier_urls,
+        verify_rate: args.verify_rate.unwrap_or(DEFAULT_VERIFY_RATE),
+        revoke_live_tokens: args.revoke_live_tokens,
+        notify,
+        notify_min_severity: args.notify_min_severity.unwrap_or(Severity::Low),
+        baseline,
+        update_baseline: args.update_baseline,
+        db: args.db.clone(),
+        report_formats: args.report_format.clone(),
+        watch,
+        fail_policy: args.fail_policy(),
+    })
+}
+
+/// The subset of the arguments needed to call the API, used by the subcommands.
+#[derive(Debug)]
+pub struct ApiArgs {
+    pub gitlab_token: String,
+    pub instance_url: Url,
+    pub http: HttpOptions,
+}
+
+pub fn validate_api_args(args: &Args) -> Result<ApiArgs, String> {
+    Ok(ApiArgs {
+        gitlab_token: args.gitlab_token()?.clone(),
+        instance_url: args.instance_url()?,
+        http: args.http_options()?,
     })
 }
 
@@ -213,11 +1038,12 @@ mod tests {
     #[test]
     fn test_valid_token() {
         let args = Args {
-            gitlab_token: FAKE_TOKEN.to_string(),
-            instance_url: "https://gitlab.com".to_string(),
+            gitlab_token: Some(FAKE_TOKEN.to_string()),
+            instance_url: Some("https://gitlab.com".to_string()),
             full_scan: true,
             group_scan: None,
             project_scan: None,
+            ..Default::default()
         };
 
         let result = args.gitlab_token();
@@ -227,11 +1053,12 @@ mod tests {
     #[test]
     fn test_invalid_token_length() {
         let args = Args {
-            gitlab_token: "glpat-too-short".to_string(),
-            instance_url: "https://gitlab.com".to_string(),
+            gitlab_token: Some("glpat-too-short".to_string()),
+            instance_url: Some("https://gitlab.com".to_string()),
             full_scan: true,
             group_scan: None,
             project_scan: None,
+            ..Default::default()
         };
 
         let result = args.gitlab_token();
@@ -242,11 +1069,12 @@ mod tests {
     #[test]
     fn test_invalid_token_prefix() {
         let args = Args {
-            gitlab_token: "token-1234567890abcdef1234".to_string(),
-            instance_url: "https://gitlab.com".to_string(),
+            gitlab_token: Some("token-1234567890abcdef1234".to_string()),
+            instance_url: Some("https://gitlab.com".to_string()),
             full_scan: true,
             group_scan: None,
             project_scan: None,
+            ..Default::default()
         };
 
         let result = args.gitlab_token();
@@ -254,14 +1082,47 @@ mod tests {
         assert_eq!(result.unwrap_err(), "Token must start with 'glpat-'");
     }
 
+    #[test]
+    fn test_missing_token() {
+        let args = Args {
+            instance_url: Some("https://gitlab.com".to_string()),
+            full_scan: true,
+            ..Default::default()
+        };
+
+        let result = args.gitlab_token();
+        assert!(result.is_err());
+        assert_eq!(
+            result.unwrap_err(),
+            "A Gitlab token is required: use --gitlab-token or a configuration profile"
+        );
+    }
+
+    #[test]
+    fn test_missing_instance_url() {
+        let args = Args {
+            gitlab_token: Some(FAKE_TOKEN.to_string()),
+            full_scan: true,
+            ..Default::default()
+        };
+
+        let result = args.instance_url();
+        assert!(result.is_err());
+        assert_eq!(
+            result.unwrap_err(),
+            "An instance URL is required: use --instance-url or a configuration profile"
+        );
+    }
+
     #[test]
     fn test_valid_instance_url() {
         let args = Args {
-            gitlab_token: FAKE_TOKEN.to_string(),
-            instance_url: "https://gitlab.com".to_string(),
+            gitlab_token: Some(FAKE_TOKEN.to_string()),
+            instance_url: Some("https://gitlab.com".to_string()),
             full_scan: true,
             group_scan: None,
             project_scan: None,
+            ..Default::default()
         };
 
         let result = args.instance_url();
@@ -272,11 +1133,12 @@ mod tests {
     #[test]
     fn test_instance_url_with_trailing_slash() {
         let args = Args {
-            gitlab_token: FAKE_TOKEN.to_string(),
-            instance_url: "https://gitlab.com/".to_string(),
+            gitlab_token: Some(FAKE_TOKEN.to_string()),
+            instance_url: Some("https://gitlab.com/".to_string()),
             full_scan: true,
             group_scan: None,
             project_scan: None,
+            ..Default::default()
         };
 
         let result = args.instance_url();
@@ -284,14 +1146,66 @@ mod tests {
         assert_eq!(result.unwrap().as_str(), "https://gitlab.com/api/v4");
     }
 
+    #[test]
+    fn test_instance_url_with_relative_root() {
+        let args = Args {
+            gitlab_token: Some(FAKE_TOKEN.to_string()),
+            instance_url: Some("https://corp.example.com/gitlab".to_string()),
+            full_scan: true,
+            ..Default::default()
+        };
+
+        let result = args.instance_url();
+        assert!(result.is_ok());
+        assert_eq!(
+            result.unwrap().as_str(),
+            "https://corp.example.com/gitlab/api/v4"
+        );
+    }
+
+    #[test]
+    fn test_instance_url_with_relative_root_and_trailing_slash() {
+        let args = Args {
+            gitlab_token: Some(FAKE_TOKEN.to_string()),
+            instance_url: Some("https://corp.example.com/tools/gitlab/".to_string()),
+            full_scan: true,
+            ..Default::default()
+        };
+
+        let result = args.instance_url();
+        assert!(result.is_ok());
+        assert_eq!(
+            result.unwrap().as_str(),
+            "https://corp.example.com/tools/gitlab/api/v4"
+        );
+    }
+
+    #[test]
+    fn test_instance_url_already_pointing_to_api() {
+        let args = Args {
+            gitlab_token: Some(FAKE_TOKEN.to_string()),
+            instance_url: Some("https://corp.example.com/gitlab/api/v4/".to_string()),
+            full_scan: true,
+            ..Default::default()
+        };
+
+        let result = args.instance_url();
+        assert!(result.is_ok());
+        assert_eq!(
+            result.unwrap().as_str(),
+            "https://corp.example.com/gitlab/api/v4"
+        );
+    }
+
     #[test]
     fn test_invalid_instance_url() {
         let args = Args {
-            gitlab_token: FAKE_TOKEN.to_string(),
-            instance_url: "invalid-url".to_string(),
+            gitlab_token: Some(FAKE_TOKEN.to_string()),
+            instance_url: Some("invalid-url".to_string()),
             full_scan: true,
             group_scan: None,
             project_scan: None,
+            ..Default::default()
         };
 
         let result = args.instance_url();
@@ -302,11 +1216,12 @@ mod tests {
     #[test]
     fn test_full_scan_type() {
         let args = Args {
-            gitlab_token: FAKE_TOKEN.to_string(),
-            instance_url: "https://gitlab.com".to_string(),
+            gitlab_token: Some(FAKE_TOKEN.to_string()),
+            instance_url: Some("https://gitlab.com".to_string()),
             full_scan: true,
             group_scan: None,
             project_scan: None,
+            ..Default::default()
         };
 
         let result = args.scan_type();
@@ -320,11 +1235,12 @@ mod tests {
     #[test]
     fn test_group_scan_type() {
         let args = Args {
-            gitlab_token: FAKE_TOKEN.to_string(),
-            instance_url: "https://gitlab.com".to_string(),
+            gitlab_token: Some(FAKE_TOKEN.to_string()),
+            instance_url: Some("https://gitlab.com".to_string()),
             full_scan: false,
             group_scan: Some(123),
             project_scan: None,
+            ..Default::default()
         };
 
         let result = args.scan_type();
@@ -338,11 +1254,12 @@ mod tests {
     #[test]
     fn test_project_scan_type() {
         let args = Args {
-            gitlab_token: FAKE_TOKEN.to_string(),
-            instance_url: "https://gitlab.com".to_string(),
+            gitlab_token: Some(FAKE_TOKEN.to_string()),
+            instance_url: Some("https://gitlab.com".to_string()),
             full_scan: false,
             group_scan: None,
             project_scan: Some(10997),
+            ..Default::default()
         };
 
         let result = args.scan_type();
@@ -356,11 +1273,12 @@ mod tests {
     #[test]
     fn test_negative_group_id() {
         let args = Args {
-            gitlab_token: FAKE_TOKEN.to_string(),
-            instance_url: "https://gitlab.com".to_string(),
+            gitlab_token: Some(FAKE_TOKEN.to_string()),
+            instance_url: Some("https://gitlab.com".to_string()),
             full_scan: false,
             group_scan: Some(-5),
             project_scan: None,
+            ..Default::default()
         };
 
         let result = args.scan_type();
@@ -371,11 +1289,12 @@ mod tests {
     #[test]
     fn test_negative_project_id() {
         let args = Args {
-            gitlab_token: FAKE_TOKEN.to_string(),
-            instance_url: "https://gitlab.com".to_string(),
+            gitlab_token: Some(FAKE_TOKEN.to_string()),
+            instance_url: Some("https://gitlab.com".to_string()),
             full_scan: false,
             group_scan: None,
             project_scan: Some(-10),
+            ..Default::default()
         };
 
         let result = args.scan_type();
@@ -386,11 +1305,12 @@ mod tests {
     #[test]
     fn test_no_scan_type() {
         let args = Args {
-            gitlab_token: FAKE_TOKEN.to_string(),
-            instance_url: "https://gitlab.com".to_string(),
+            gitlab_token: Some(FAKE_TOKEN.to_string()),
+            instance_url: Some("https://gitlab.com".to_string()),
             full_scan: false,
             group_scan: None,
             project_scan: None,
+            ..Default::default()
         };
 
         let result = args.scan_type();
@@ -404,11 +1324,12 @@ mod tests {
     #[test]
     fn test_multiple_scan_types() {
         let args = Args {
-            gitlab_token: FAKE_TOKEN.to_string(),
-            instance_url: "https://gitlab.com".to_string(),
+            gitlab_token: Some(FAKE_TOKEN.to_string()),
+            instance_url: Some("https://gitlab.com".to_string()),
             full_scan: true,
             group_scan: Some(123),
             project_scan: None,
+            ..Default::default()
         };
 
         let result = args.scan_type();
@@ -419,43 +1340,171 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_valid_socks_proxy() {
+        let args = Args {
+            proxy: Some("socks5h://proxy.corp.example.com:1080".to_string()),
+            no_proxy: Some("localhost,.corp.example.com".to_string()),
+            ..Default::default()
+        };
+
+        let result = args.http_options();
+        assert!(result.is_ok());
+        assert_eq!(
+            result.unwrap().no_proxy.as_deref(),
+            Some("localhost,.corp.example.com")
+        );
+    }
+
+    #[test]
+    fn test_invalid_proxy_scheme() {
+        let args = Args {
+            proxy: Some("ftp://proxy.corp.example.com".to_string()),
+            ..Default::default()
+        };
+
+        let result = args.http_options();
+        assert!(result.is_err());
+        assert_eq!(
+            result.unwrap_err(),
+            "Proxy scheme must be one of: http, https, socks5, socks5h"
+        );
+    }
+
+    #[test]
+    fn test_client_cert_without_key() {
+        let args = Args {
+            client_cert: Some(PathBuf::from("client.pem")),
+            ..Default::default()
+        };
+
+        let result = args.http_options();
+        assert!(result.is_err());
+        assert_eq!(
+            result.unwrap_err(),
+            "--client-cert and --client-key must be provided together"
+        );
+    }
+
+    #[test]
+    fn test_invalid_encryption_recipient() {
+        let args = Args {
+            encrypt_to: vec!["ssh-rsa AAAA".to_string()],
+            ..Default::default()
+        };
+
+        let result = args.encrypt_to();
+        assert!(result.is_err());
+        assert!(result.unwrap_err().starts_with("Invalid age recipient"));
+    }
+
+    #[test]
+    fn test_watch_interval() {
+        let args = Args {
+            watch: Some(0),
+            ..Default::default()
+        };
+        assert!(args.watch().is_err());
+
+        let args = Args {
+            watch: Some(15),
+            ..Default::default()
+        };
+        assert_eq!(args.watch(), Ok(Some(Duration::from_secs(900))));
+    }
+
+    #[test]
+    fn test_job_filter() {
+        let args = Args {
+            since: Some("2025-03-01".to_string()),
+            until: Some("2025-03-31".to_string()),
+            git_ref: Some("release/*".to_string()),
+            job_name: Some("^deploy".to_string()),
+            ..Default::default()
+        };
+        let filter = args.job_filter().unwrap();
+        assert!(filter.since < filter.until);
+        assert_eq!(filter.git_ref.unwrap().pattern, "release/*");
+
+        let args = Args {
+            since: Some("2025-03-31".to_string()),
+            until: Some("2025-03-01".to_string()),
+            ..Default::default()
+        };
+        assert!(args.job_filter().is_err());
+
+        let args = Args {
+            job_name: Some("deploy(".to_string()),
+            ..Default::default()
+        };
+        assert!(args.job_filter().is_err());
+    }
+
+    #[test]
+    fn test_update_baseline_requires_baseline() {
+        let mut args = Args {
+            update_baseline: true,
+            ..Default::default()
+        };
+        assert!(args.baseline().is_err());
+
+        args.baseline = Some(PathBuf::from("baseline.json"));
+        assert_eq!(args.baseline(), Ok(Some(PathBuf::from("baseline.json"))));
+    }
+
     #[test]
     fn test_validate_args_success() {
         let args = Args {
-            gitlab_token: FAKE_TOKEN.to_string(),
-            instance_url: "https://gitlab.com".to_string(),
+            gitlab_token: Some(FAKE_TOKEN.to_string()),
+            instance_url: Some("https://gitlab.com".to_string()),
             full_scan: true,
             group_scan: None,
             project_scan: None,
+            ..Default::default()
         };
 
         let result = validate_args(&args);
         assert!(result.is_ok());
+        assert_eq!(result.unwrap().output_dir, PathBuf::from("results"));
     }
 
     #[test]
     fn test_validate_args_failure() {
         let args = Args {
-            gitlab_token: "invalid-token".to_string(),
-            instance_url: "https://gitlab.com".to_string(),
+            gitlab_token: Some("invalid-token".to_string()),
+            instance_url: Some("https://gitlab.com".to_string()),
             full_scan: true,
             group_scan: None,
             project_scan: None,
+            ..Default::default()
         };
 
         let result = validate_args(&args);
         assert!(result.is_err());
     }
 
+    #[test]
+    fn test_validate_api_args_without_scan_type() {
+        let args = Args {
+            gitlab_token: Some(FAKE_TOKEN.to_string()),
+            instance_url: Some("https://gitlab.com".to_string()),
+            ..Default::default()
+        };
+
+        assert!(validate_args(&args).is_err());
+        assert!(validate_api_args(&args).is_ok());
+    }
+
     #[test]
     #[should_panic(expected = "Invalid arguments")]
     fn test_return_args_with_invalid_args() {
         let args = Args {
-            gitlab_token: "invalid-token".to_string(),
-            instance_url: "https://gitlab.com".to_string(),
+            gitlab_token: Some("invalid-token".to_string()),
+            instance_url: Some("https://gitlab.com".to_string()),
             full_scan: true,
             group_scan: None,
             project_scan: None,
+            ..Default::default()
         };
 
         return_args(args);
@@ -0,0 +1,20 @@
+use std::io::Write;
+use std::path::Path;
+
+use crate::crypto::{decrypt, load_identities};
+
+/// Decrypts a file to `output`, or to the standard output when no file is given.
+pub fn run_decrypt(identity: &Path, file: &Path, output: Option<&Path>) -> Result<(), String> {
+    let identities = load_identities(identity)?;
+    let content =
+        std::fs::read(file).map_err(|e| format!("Failed to read {}: {}", file.display(), e))?;
+    let plaintext = decrypt(&identities, &content)?;
+
+    match output {
+        Some(path) => std::fs::write(path, plaintext)
+            .map_err(|e| format!("Failed to write {}: {}", path.display(), e)),
+        None => std::io::stdout()
+            .write_all(&plaintext)
+            .map_err(|e| format!("Failed to write to the standard output: {}", e)),
+    }
+}
@@ -0,0 +1,202 @@
+use std::collections::HashSet;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use colored::Colorize;
+
+use crate::crypto::{ENCRYPTED_EXTENSION, encrypt, load_identities, parse_recipients};
+use crate::output::{MANIFEST_FILE, Manifest, find_artifact};
+use crate::report::diff::diff_reports;
+use crate::report::{REPORT_FILE, Report, ReportFormat};
+
+/// Whether a report, or the report of a run directory, was written with `--encrypt-to`.
+fn is_encrypted(path: &Path) -> bool {
+    let report = if path.is_dir() {
+        find_artifact(path, REPORT_FILE)
+    } else {
+        Some(path.to_path_buf())
+    };
+
+    report.is_some_and(|report| {
+        report
+            .extension()
+            .is_some_and(|extension| extension == ENCRYPTED_EXTENSION)
+    })
+}
+
+/// Compares two reports, or the reports of two run directories, by finding fingerprint. A
+/// comparison written to a file is encrypted with `--encrypt-to`, which encrypted reports require.
+pub fn run_diff(
+    old: &Path,
+    new: &Path,
+    identity: Option<&Path>,
+    format: ReportFormat,
+    output: Option<&Path>,
+    encrypt_to: &[String],
+) -> Result<(), String> {
+    let recipients = parse_recipients(encrypt_to)?;
+    if output.is_some() && recipients.is_empty() && (is_encrypted(old) || is_encrypted(new)) {
+        return Err(
+            "The reports are encrypted, use --encrypt-to so that the comparison is encrypted too"
+                .to_string(),
+        );
+    }
+
+    let identities = match identity {
+        Some(path) => load_identities(path)?,
+        None => Vec::new(),
+    };
+    let old = Report::read(old, &identities)?;
+    let new_report = Report::read(new, &identities)?;
+
+    let rescanned_jobs = rescanned_jobs(new, &new_report, &identities)?;
+
+    let content = diff_reports(&old, &new_report, &rescanned_jobs).render(format)?;
+
+    match output {
+        Some(path) if !recipients.is_empty() => {
+            let mut encrypted = PathBuf::from(path);
+            if path.extension().is_none_or(|e| e != ENCRYPTED_EXTENSION) {
+                encrypted
+                    .as_mut_os_string()
+                    .push(format!(".{}", ENCRYPTED_EXTENSION));
+            }
+            std::fs::write(&encrypted, encrypt(&recipients, content.as_bytes())?)
+                .map_err(|e| format!("Failed to write {}: {}", encrypted.display(), e))
+        }
+        Some(path) => std::fs::write(path, content)
+            .map_err(|e| format!("Failed to write {}: {}", path.display(), e)),
+        None => writeln!(std::io::stdout(), "{}", content)
+            .map_err(|e| format!("Failed to write to the standard output: {}", e)),
+    }
+}
+
+/// The jobs scanned by the new run, a finding is only resolved when its job was scanned again.
+/// A report file without its manifest only tells the jobs with findings.
+fn rescanned_jobs(
+    new: &Path,
+    new_report: &Report,
+    identities: &[Box<dyn age::Identity>],
+) -> Result<HashSet<u64>, String> {
+    let run_dir = if new.is_dir() {
+        new
+    } else {
+        new.parent().unwrap_or(Path::new("."))
+    };
+
+    // The warning goes to the standard error, the standard output may carry the comparison.
+    if find_artifact(run_dir, MANIFEST_FILE).is_none() {
+        eprintln!(
+            "{}",
+            format!(
+                "No {} found in {}, only the jobs with findings in the new report count as rescanned.",
+                MANIFEST_FILE,
+                run_dir.display()
+            )
+            .yellow()
+        );
+        return Ok(new_report.findings.iter().map(|f| f.job_id).collect());
+    }
+
+    Ok(Manifest::read(run_dir, identities)?
+        .entries
+        .iter()
+        .map(|entry| entry.job_id)
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::detect::test_finding;
+    use crate::report::diff::ReportDiff;
+
+    #[test]
+    fn test_diff_of_two_report_files() {
+        let dir = std::env::temp_dir().join(format!("gitlab_auditor_diff_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let write = |name: &str, findings| {
+            let report = Report {
+                findings,
+                ..Default::default()
+            };
+            let path = dir.join(name);
+            std::fs::write(&path, serde_json::to_string(&report).unwrap()).unwrap();
+            path
+        };
+        let old = write(
+            "old.json",
+            vec![
+                test_finding(1, 10, 1),
+                test_finding(1, 10, 2),
+                test_finding(2, 20, 1),
+            ],
+        );
+        let new = write("new.json", vec![test_finding(1, 10, 1)]);
+        let output = dir.join("diff.json");
+
+        let result = run_diff(&old, &new, None, ReportFormat::Json, Some(&output), &[]);
+        let diff: Option<ReportDiff> = std::fs::read(&output)
+            .ok()
+            .and_then(|content| serde_json::from_slice(&content).ok());
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        // Without a manifest, job 10 still has findings so it was rescanned, job 20 may not be.
+        assert!(result.is_ok());
+        let diff = diff.unwrap();
+        assert_eq!(diff.summary.still_present, 1);
+        assert_eq!(diff.summary.resolved, 1);
+        assert_eq!(diff.summary.not_rescanned, 1);
+        assert_eq!(diff.not_rescanned[0].job_id, 20);
+    }
+
+    #[test]
+    fn test_diff_of_encrypted_reports_is_written_encrypted() {
+        use age::secrecy::ExposeSecret;
+        use age::x25519;
+
+        use crate::crypto::decrypt;
+
+        let dir = std::env::temp_dir().join(format!(
+            "gitlab_auditor_diff_encrypted_{}",
+            std::process::id()
+        ));
+        std::fs::create_dir_all(&dir).unwrap();
+        let identity = x25519::Identity::generate();
+        let recipients = vec![identity.to_public()];
+        let report = serde_json::to_vec(&Report {
+            findings: vec![test_finding(1, 10, 1)],
+            ..Default::default()
+        })
+        .unwrap();
+        let old = dir.join("old.json.age");
+        let new = dir.join("new.json.age");
+        for path in [&old, &new] {
+            std::fs::write(path, encrypt(&recipients, &report).unwrap()).unwrap();
+        }
+        let identity_file = dir.join("key.txt");
+        std::fs::write(&identity_file, identity.to_string().expose_secret()).unwrap();
+        let output = dir.join("diff.json");
+
+        let refused = run_diff(&old, &new, None, ReportFormat::Json, Some(&output), &[]);
+        let written = run_diff(
+            &old,
+            &new,
+            Some(&identity_file),
+            ReportFormat::Json,
+            Some(&output),
+            &[identity.to_public().to_string()],
+        );
+        let plaintext_written = output.exists();
+        let content = std::fs::read(dir.join("diff.json.age"));
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert!(refused.unwrap_err().contains("--encrypt-to"));
+        assert!(written.is_ok());
+        assert!(!plaintext_written);
+        let identities: Vec<Box<dyn age::Identity>> = vec![Box::new(identity)];
+        let diff: ReportDiff =
+            serde_json::from_slice(&decrypt(&identities, &content.unwrap()).unwrap()).unwrap();
+        assert_eq!(diff.summary.still_present, 1);
+    }
+}
@@ -0,0 +1,116 @@
+pub mod analyze;
+pub mod ci;
+pub mod decrypt;
+pub mod diff;
+pub mod query;
+pub mod remediate;
+pub mod serve;
+
+use colored::Colorize;
+
+use crate::cli::{ApiArgs, Args, Command, RemediateAction, validate_api_args};
+use crate::http::{GitlabClient, build_client};
+use crate::policy::Exit;
+use crate::scans::shared::detect_api_root;
+use crate::store::FindingFilter;
+
+/// Runs a subcommand, the scanning ones exit according to `--fail-on` and `--max-findings`.
+pub async fn run(command: Command, args: &Args) -> Result<Exit, String> {
+    match command {
+        Command::Analyze { identity, run_dir } => {
+            analyze::run_analyze(&run_dir, identity.as_deref(), args)
+        }
+        Command::Ci { output } => ci::run_ci(args, &output).await,
+        Command::Decrypt {
+            identity,
+            output,
+            file,
+        } => decrypt::run_decrypt(&identity, &file, output.as_deref()).map(|()| Exit::Success),
+        Command::Diff {
+            identity,
+            format,
+            output,
+            old,
+            new,
+        } => diff::run_diff(
+            &old,
+            &new,
+            identity.as_deref(),
+            format,
+            output.as_deref(),
+            &args.encrypt_to,
+        )
+        .map(|()| Exit::Success),
+        Command::Query {
+            db,
+            project,
+            rule,
+            severity,
+            since,
+            until,
+            sql,
+        } => {
+            let filter = FindingFilter {
+                project,
+                rule,
+                min_severity: severity,
+                since,
+                until,
+            };
+            query::run_query(&db, &filter, sql.as_deref()).map(|()| Exit::Success)
+        }
+        Command::Serve {
+            listen,
+            secret_token,
+            queue_size,
+        } => serve::run_serve(args, listen, secret_token, queue_size)
+            .await
+            .map(|failed| {
+                if failed > 0 {
+                    Exit::Partial
+                } else {
+                    Exit::Success
+                }
+            }),
+        Command::Remediate { action } => match action {
+            RemediateAction::Erase {
+                report,
+                identity,
+                dry_run,
+                yes,
+                audit_log,
+            } => {
+                let gitlab = connect(&validate_api_args(args)?).await?;
+                remediate::run_erase(
+                    &gitlab,
+                    &report,
+                    identity.as_deref(),
+                    dry_run,
+                    yes,
+                    &audit_log,
+                )
+                .await
+                .map(|failed| {
+                    if failed > 0 {
+                        Exit::Partial
+                    } else {
+                        Exit::Success
+                    }
+                })
+            }
+        },
+    }
+}
+
+/// Builds the configured client and binds it to the detected API root.
+pub async fn connect(api: &ApiArgs) -> Result<GitlabClient, String> {
+    let http = build_client(&api.http)?;
+    let (api_root, version) = detect_api_root(&http, &api.gitlab_token, &api.instance_url).await?;
+
+    println!(
+        "{}",
+        format!("Connected to {} (Gitlab {}).", api_root, version.version).blue()
+    );
+
+    Ok(GitlabClient::new(http, &api.gitlab_token, &api_root))
+}
@@ -0,0 +1,20 @@
+use std::io::Write;
+use std::path::Path;
+
+use crate::store::{FindingFilter, Store};
+
+/// Prints the findings matching the filters, or the result of a raw SQL query, as TSV.
+pub fn run_query(db: &Path, filter: &FindingFilter, sql: Option<&str>) -> Result<(), String> {
+    if !db.exists() {
+        return Err(format!("Database {} not found", db.display()));
+    }
+    let store = Store::open_read_only(db)?;
+
+    let result = match sql {
+        Some(sql) => store.query_sql(sql)?,
+        None => store.query_findings(filter)?,
+    };
+
+    writeln!(std::io::stdout(), "{}", result.to_tsv())
+        .map_err(|e| format!("Failed to write to the standard output: {}", e))
+}
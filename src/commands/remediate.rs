@@ -0,0 +1,255 @@
+use std::collections::BTreeMap;
+use std::io::{BufRead, Write};
+use std::path::Path;
+
+use chrono::Utc;
+use colored::Colorize;
+use serde::Serialize;
+
+use crate::crypto::load_identities;
+use crate::detect::Finding;
+use crate::http::GitlabClient;
+use crate::report::Report;
+
+/// A job whose log holds at least one finding.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ErasePlanItem {
+    pub project_id: u64,
+    pub project_path: String,
+    pub job_id: u64,
+    pub job_web_url: String,
+    pub findings: usize,
+}
+
+/// One job per line, ordered by project then job, whatever the number of findings in it.
+pub fn erase_plan(findings: &[Finding]) -> Vec<ErasePlanItem> {
+    let mut plan: BTreeMap<(u64, u64), ErasePlanItem> = BTreeMap::new();
+
+    for finding in findings {
+        plan.entry((finding.project_id, finding.job_id))
+            .or_insert_with(|| ErasePlanItem {
+                project_id: finding.project_id,
+                project_path: finding.project_path.clone(),
+                job_id: finding.job_id,
+                job_web_url: finding.job_web_url.clone(),
+                findings: 0,
+            })
+            .findings += 1;
+    }
+
+    plan.into_values().collect()
+}
+
+#[derive(Debug, Serialize)]
+pub struct AuditRecord<'a> {
+    pub timestamp: String,
+    pub action: &'a str,
+    pub status: &'a str,
+    pub project_id: u64,
+    pub project_path: &'a str,
+    pub job_id: u64,
+    pub job_web_url: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+}
+
+/// Appends remediation actions to a JSON lines file, it is never truncated.
+pub struct AuditLog {
+    file: std::fs::File,
+}
+
+impl AuditLog {
+    pub fn open(path: &Path) -> Result<Self, String> {
+        let file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .map_err(|e| format!("Failed to open audit log {}: {}", path.display(), e))?;
+
+        Ok(AuditLog { file })
+    }
+
+    pub fn record(
+        &mut self,
+        action: &str,
+        status: &str,
+        item: &ErasePlanItem,
+        detail: Option<String>,
+    ) -> Result<(), String> {
+        let record = AuditRecord {
+            timestamp: Utc::now().to_rfc3339(),
+            action,
+            status,
+            project_id: item.project_id,
+            project_path: &item.project_path,
+            job_id: item.job_id,
+            job_web_url: &item.job_web_url,
+            detail,
+        };
+        let line = serde_json::to_string(&record)
+            .map_err(|e| format!("Failed to serialize audit record: {}", e))?;
+
+        writeln!(self.file, "{}", line).map_err(|e| format!("Failed to write audit log: {}", e))
+    }
+}
+
+pub async fn erase_job_log(
+    gitlab: &GitlabClient,
+    project_id: u64,
+    job_id: u64,
+) -> Result<(), reqwest::Error> {
+    gitlab
+        .post(&format!("/projects/{}/jobs/{}/erase", project_id, job_id))
+        .send()
+        .await?
+        .error_for_status()?;
+
+    Ok(())
+}
+
+fn confirm(prompt: &str) -> bool {
+    print!("{} [y/N] ", prompt);
+    let _ = std::io::stdout().flush();
+
+    let mut answer = String::new();
+    std::io::stdin().lock().read_line(&mut answer).is_ok()
+        && matches!(answer.trim().to_lowercase().as_str(), "y" | "yes")
+}
+
+/// Erases the logs of the jobs with findings in a report and returns how many could not be erased.
+pub async fn run_erase(
+    gitlab: &GitlabClient,
+    report: &Path,
+    identity: Option<&Path>,
+    dry_run: bool,
+    yes: bool,
+    audit_log: &Path,
+) -> Result<usize, String> {
+    let identities = match identity {
+        Some(path) => load_identities(path)?,
+        None => Vec::new(),
+    };
+    let report = Report::read(report, &identities)?;
+    let plan = erase_plan(&report.findings);
+
+    if plan.is_empty() {
+        println!("{}", "No findings in the report, nothing to erase.".green());
+        return Ok(0);
+    }
+
+    println!(
+        "{}",
+        format!("Erase plan: {} job logs", plan.len()).bold().blue()
+    );
+    for item in &plan {
+        println!(
+            "   {} job {} ({} findings) {}",
+            item.project_path, item.job_id, item.findings, item.job_web_url
+        );
+    }
+
+    let mut audit = AuditLog::open(audit_log)?;
+
+    if dry_run {
+        for item in &plan {
+            audit.record("erase", "dry-run", item, None)?;
+        }
+        println!("{}", "Dry run, nothing was erased.".yellow());
+        return Ok(0);
+    }
+
+    if !yes
+        && !confirm(&format!(
+            "Erase the logs of {} jobs? This cannot be undone.",
+            plan.len()
+        ))
+    {
+        for item in &plan {
+            audit.record("erase", "aborted", item, None)?;
+        }
+        println!("{}", "Aborted, nothing was erased.".yellow());
+        return Ok(0);
+    }
+
+    let mut erased = 0;
+    for item in &plan {
+        match erase_job_log(gitlab, item.project_id, item.job_id).await {
+            Ok(()) => {
+                erased += 1;
+                audit.record("erase", "erased", item, None)?;
+                println!(
+                    "{}",
+                    format!("   Erased job {} of {}", item.job_id, item.project_path).blue()
+                );
+            }
+            Err(e) => {
+                audit.record("erase", "failed", item, Some(e.to_string()))?;
+                println!(
+                    "{}",
+                    format!("   Failed to erase job {}: {}", item.job_id, e).red()
+                );
+            }
+        }
+    }
+
+    println!(
+        "{}",
+        format!(
+            "Erased {}/{} job logs, actions recorded in {}.",
+            erased,
+            plan.len(),
+            audit_log.display()
+        )
+        .green()
+        .bold()
+    );
+
+    Ok(plan.len() - erased)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::detect::test_finding as finding;
+
+    #[test]
+    fn test_erase_plan_has_one_item_per_job() {
+        let findings = vec![
+            finding(2, 20, 1),
+            finding(1, 10, 4),
+            finding(2, 20, 8),
+            finding(1, 11, 2),
+        ];
+
+        let plan = erase_plan(&findings);
+
+        assert_eq!(
+            plan.iter()
+                .map(|i| (i.project_id, i.job_id, i.findings))
+                .collect::<Vec<_>>(),
+            vec![(1, 10, 1), (1, 11, 1), (2, 20, 2)]
+        );
+    }
+
+    #[test]
+    fn test_audit_log_appends() {
+        let path =
+            std::env::temp_dir().join(format!("gitlab_auditor_audit_{}.jsonl", std::process::id()));
+        let item = &erase_plan(&[finding(1, 10, 1)])[0];
+
+        for status in ["dry-run", "erased"] {
+            AuditLog::open(&path)
+                .unwrap()
+                .record("erase", status, item, None)
+                .unwrap();
+        }
+
+        let content = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let lines: Vec<_> = content.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[1].contains(r#""status":"erased""#));
+        assert!(lines[1].contains(r#""job_id":10"#));
+    }
+}
@@ -0,0 +1,118 @@
+use std::io::{Read, Write};
+use std::path::Path;
+use std::str::FromStr;
+
+use age::x25519;
+
+pub const ENCRYPTED_EXTENSION: &str = "age";
+
+pub fn parse_recipients(values: &[String]) -> Result<Vec<x25519::Recipient>, String> {
+    values
+        .iter()
+        .map(|value| {
+            x25519::Recipient::from_str(value.trim())
+                .map_err(|e| format!("Invalid age recipient '{}': {}", value, e))
+        })
+        .collect()
+}
+
+/// Reads an age identity file, as created by `age-keygen`.
+pub fn load_identities(path: &Path) -> Result<Vec<Box<dyn age::Identity>>, String> {
+    age::IdentityFile::from_file(path.display().to_string())
+        .map_err(|e| format!("Failed to read identity file {}: {}", path.display(), e))?
+        .into_identities()
+        .map_err(|e| format!("Invalid identity file {}: {}", path.display(), e))
+}
+
+/// Encrypts the content in memory to every recipient, any of them can decrypt it.
+pub fn encrypt(recipients: &[x25519::Recipient], content: &[u8]) -> Result<Vec<u8>, String> {
+    let encryptor =
+        age::Encryptor::with_recipients(recipients.iter().map(|r| r as &dyn age::Recipient))
+            .map_err(|e| format!("Failed to encrypt: {}", e))?;
+
+    let mut encrypted = Vec::new();
+    let mut writer = encryptor
+        .wrap_output(&mut encrypted)
+        .map_err(|e| format!("Failed to encrypt: {}", e))?;
+    writer
+        .write_all(content)
+        .and_then(|_| writer.finish())
+        .map_err(|e| format!("Failed to encrypt: {}", e))?;
+
+    Ok(encrypted)
+}
+
+pub fn decrypt(identities: &[Box<dyn age::Identity>], content: &[u8]) -> Result<Vec<u8>, String> {
+    let decryptor =
+        age::Decryptor::new(content).map_err(|e| format!("Failed to decrypt: {}", e))?;
+
+    let mut reader = decryptor
+        .decrypt(identities.iter().map(|i| i.as_ref()))
+        .map_err(|e| format!("Failed to decrypt: {}", e))?;
+
+    let mut decrypted = Vec::new();
+    reader
+        .read_to_end(&mut decrypted)
+        .map_err(|e| format!("Failed to decrypt: {}", e))?;
+
+    Ok(decrypted)
+}
+
+/// Reads a file written by the auditor, decrypting it when it has the `.age` extension.
+pub fn read_artifact(
+    path: &Path,
+    identities: &[Box<dyn age::Identity>],
+) -> Result<Vec<u8>, String> {
+    let content =
+        std::fs::read(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+
+    if path
+        .extension()
+        .is_some_and(|ext| ext == ENCRYPTED_EXTENSION)
+    {
+        if identities.is_empty() {
+            return Err(format!(
+                "{} is encrypted, an identity file is required",
+                path.display()
+            ));
+        }
+        decrypt(identities, &content).map_err(|e| format!("{} ({})", e, path.display()))
+    } else {
+        Ok(content)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encrypt_to_several_recipients() {
+        let first = x25519::Identity::generate();
+        let second = x25519::Identity::generate();
+        let recipients = vec![first.to_public(), second.to_public()];
+
+        let encrypted = encrypt(&recipients, b"glpat-secret").unwrap();
+        assert!(!encrypted.windows(12).any(|w| w == b"glpat-secret"));
+
+        for identity in [first, second] {
+            let identities: Vec<Box<dyn age::Identity>> = vec![Box::new(identity)];
+            assert_eq!(decrypt(&identities, &encrypted).unwrap(), b"glpat-secret");
+        }
+    }
+
+    #[test]
+    fn test_decrypt_with_wrong_identity() {
+        let recipients = vec![x25519::Identity::generate().to_public()];
+        let encrypted = encrypt(&recipients, b"glpat-secret").unwrap();
+
+        let identities: Vec<Box<dyn age::Identity>> = vec![Box::new(x25519::Identity::generate())];
+        assert!(decrypt(&identities, &encrypted).is_err());
+    }
+
+    #[test]
+    fn test_invalid_recipient() {
+        let result = parse_recipients(&["age1invalid".to_string()]);
+        assert!(result.is_err());
+    }
+}
@@ -0,0 +1,171 @@
+use std::path::PathBuf;
+
+use colored::Colorize;
+use reqwest::{Certificate, Identity, Method, NoProxy, Proxy, RequestBuilder};
+use serde::de::DeserializeOwned;
+use url::Url;
+
+use crate::scans::shared::{JOB_TOKEN_HEADER, PRIVATE_TOKEN_HEADER};
+
+const PER_PAGE: &str = "100";
+
+/// Transport settings applied to every request sent by the auditor.
+#[derive(Debug, Clone, Default)]
+pub struct HttpOptions {
+    pub ca_certs: Vec<PathBuf>,
+    pub client_cert: Option<PathBuf>,
+    pub client_key: Option<PathBuf>,
+    pub proxy: Option<String>,
+    pub no_proxy: Option<String>,
+    pub insecure: bool,
+}
+
+pub fn build_client(options: &HttpOptions) -> Result<reqwest::Client, String> {
+    let mut builder = reqwest::Client::builder();
+
+    for path in &options.ca_certs {
+        let pem = read_file(path, "CA certificate")?;
+        // A bundle may contain several certificates, each of them is trusted.
+        let certs = Certificate::from_pem_bundle(&pem)
+            .map_err(|e| format!("Invalid CA certificate {}: {}", path.display(), e))?;
+        for cert in certs {
+            builder = builder.add_root_certificate(cert);
+        }
+    }
+
+    match (&options.client_cert, &options.client_key) {
+        (Some(cert_path), Some(key_path)) => {
+            let cert = read_file(cert_path, "client certificate")?;
+            let key = read_file(key_path, "client key")?;
+            let identity = Identity::from_pkcs8_pem(&cert, &key).map_err(|e| {
+                format!(
+                    "Invalid client certificate or key (expected PEM, PKCS#8 key): {}",
+                    e
+                )
+            })?;
+            builder = builder.identity(identity);
+        }
+        (None, None) => {}
+        _ => {
+            return Err("--client-cert and --client-key must be provided together".to_string());
+        }
+    }
+
+    if let Some(proxy_url) = &options.proxy {
+        // An explicit NO_PROXY list wins over the environment one.
+        let no_proxy = match &options.no_proxy {
+            Some(list) => NoProxy::from_string(list),
+            None => NoProxy::from_env(),
+        };
+        let proxy = Proxy::all(proxy_url)
+            .map_err(|e| format!("Invalid proxy URL {}: {}", proxy_url, e))?
+            .no_proxy(no_proxy);
+        builder = builder.proxy(proxy);
+    }
+
+    if options.insecure {
+        println!(
+            "{}",
+            "WARNING: TLS certificate verification is disabled (--insecure), the connection to the instance can be intercepted."
+                .yellow()
+                .bold()
+        );
+        builder = builder.danger_accept_invalid_certs(true);
+    }
+
+    builder
+        .build()
+        .map_err(|e| format!("Failed to build the HTTP client: {}", e))
+}
+
+fn read_file(path: &PathBuf, kind: &str) -> Result<Vec<u8>, String> {
+    std::fs::read(path).map_err(|e| format!("Failed to read {} {}: {}", kind, path.display(), e))
+}
+
+/// A configured HTTP client bound to the API root of one Gitlab instance.
+#[derive(Debug, Clone)]
+pub struct GitlabClient {
+    pub http: reqwest::Client,
+    pub token: String,
+    pub url: Url,
+    // `PRIVATE-TOKEN` for access tokens, `JOB-TOKEN` for the CI_JOB_TOKEN of a running job.
+    pub token_header: &'static str,
+}
+
+impl GitlabClient {
+    pub fn new(http: reqwest::Client, token: &str, url: &Url) -> Self {
+        GitlabClient {
+            http,
+            token: token.to_string(),
+            url: url.clone(),
+            token_header: PRIVATE_TOKEN_HEADER,
+        }
+    }
+
+    pub fn with_job_token(http: reqwest::Client, token: &str, url: &Url) -> Self {
+        GitlabClient {
+            token_header: JOB_TOKEN_HEADER,
+            ..GitlabClient::new(http, token, url)
+        }
+    }
+
+    /// Builds an authenticated request for an API path such as `/groups`.
+    pub fn request(&self, method: Method, path: &str) -> RequestBuilder {
+        self.http
+            .request(method, format!("{}{}", self.url, path))
+            .header(self.token_header, &self.token)
+    }
+
+    pub fn get(&self, path: &str) -> RequestBuilder {
+        self.request(Method::GET, path)
+    }
+
+    pub fn post(&self, path: &str) -> RequestBuilder {
+        self.request(Method::POST, path)
+    }
+
+    pub fn put(&self, path: &str) -> RequestBuilder {
+        self.request(Method::PUT, path)
+    }
+
+    pub fn delete(&self, path: &str) -> RequestBuilder {
+        self.request(Method::DELETE, path)
+    }
+
+    /// Fetches every page of a list endpoint by following the `x-next-page` header.
+    pub async fn get_all_pages<T: DeserializeOwned>(
+        &self,
+        path: &str,
+        query: &[(&str, &str)],
+    ) -> Result<Vec<T>, reqwest::Error> {
+        let mut items = Vec::new();
+        let mut page = 1;
+
+        loop {
+            let response = self
+                .get(path)
+                .query(query)
+                .query(&[("per_page", PER_PAGE), ("page", &page.to_string())])
+                .send()
+                .await?
+                .error_for_status()?;
+
+            let next_page = response
+                .headers()
+                .get("x-next-page")
+                .and_then(|h| h.to_str().ok())
+                .and_then(|s| s.parse::<u32>().ok());
+
+            let mut page_items: Vec<T> = response.json().await?;
+            let page_len = page_items.len();
+            items.append(&mut page_items);
+
+            match next_page {
+                Some(next) if page_len > 0 => page = next,
+                _ => break,
+            }
+        }
+
+        Ok(items)
+    }
+}
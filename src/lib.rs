@@ -1,2 +1,15 @@
+pub mod baseline;
 pub mod cli;
+pub mod commands;
+pub mod config;
+pub mod crypto;
+pub mod detect;
+pub mod http;
+pub mod issues;
+pub mod notify;
+pub mod output;
+pub mod policy;
+pub mod report;
 pub mod scans;
+pub mod store;
+pub mod verify;
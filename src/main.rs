@@ -1,48 +1,582 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
 use clap::Parser;
 use colored::Colorize;
+use gitlab_auditor::baseline::Baseline;
+use gitlab_auditor::baseline::suppress;
 use gitlab_auditor::cli::Args;
+use gitlab_auditor::cli::Command;
+use gitlab_auditor::cli::ValidatedArgs;
 use gitlab_auditor::cli::print_banner;
-use gitlab_auditor::cli::return_args;
+use gitlab_auditor::cli::validate_args;
+use gitlab_auditor::commands::run;
+use gitlab_auditor::config::load_profile;
+use gitlab_auditor::crypto::parse_recipients;
+use gitlab_auditor::detect::Detector;
+use gitlab_auditor::http::GitlabClient;
+use gitlab_auditor::http::build_client;
+use gitlab_auditor::issues::sync_issues;
+use gitlab_auditor::notify::{NOTIFIED_FILE, NotifiedState, notify, sink_client};
+use gitlab_auditor::output::ArtifactWriter;
+use gitlab_auditor::output::clean_output_dir;
+use gitlab_auditor::output::create_run_dir;
+use gitlab_auditor::policy::Exit;
+use gitlab_auditor::report::Report;
+use gitlab_auditor::report::Summary;
 use gitlab_auditor::scans::full::fetch_groups;
 use gitlab_auditor::scans::full::fetch_job_traces_for_projects;
 use gitlab_auditor::scans::full::fetch_projects_from_groups;
+use gitlab_auditor::scans::full::fetch_projects_outside_groups;
+use gitlab_auditor::scans::namespace::NamespaceTree;
+use gitlab_auditor::scans::namespace::merge_projects;
+use gitlab_auditor::scans::shared::detect_api_root;
+use gitlab_auditor::scans::trace::TraceProcessor;
+use gitlab_auditor::scans::watch::{PolledJob, WatchState};
+use gitlab_auditor::store::RunRecord;
+use gitlab_auditor::store::Store;
+use gitlab_auditor::verify::RateLimiter;
+use gitlab_auditor::verify::build_verifiers;
+use gitlab_auditor::verify::gitlab::{is_admin, revoke_live_tokens};
+use gitlab_auditor::verify::verify_findings;
 
 #[tokio::main]
-async fn main() {
+async fn main() -> ExitCode {
+    let mut args = Args::parse();
+
+    // Local commands print to the standard output, they run without the banner and the profile.
+    if let Some(
+        command @ (Command::Decrypt { .. } | Command::Diff { .. } | Command::Query { .. }),
+    ) = args.command.clone()
+    {
+        return match run(command, &args).await {
+            Ok(exit) => exit.into(),
+            Err(e) => {
+                eprintln!("{}", e.red());
+                Exit::Fatal.into()
+            }
+        };
+    }
+
     print_banner();
-    let args = return_args(Args::parse());
+
+    let profile = match load_profile(&mut args) {
+        Ok(profile) => profile,
+        Err(e) => {
+            println!("Error loading configuration: {}", e);
+            return Exit::Fatal.into();
+        }
+    };
+
+    if let Some(command) = args.command.clone() {
+        return match run(command, &args).await {
+            Ok(exit) => exit.into(),
+            Err(e) => {
+                println!("{}", e.red());
+                Exit::Fatal.into()
+            }
+        };
+    }
+
+    let mut args = match validate_args(&args) {
+        Ok(args) => args,
+        Err(e) => {
+            println!("Invalid arguments: {}", e);
+            return Exit::Fatal.into();
+        }
+    };
 
     println!("{}", "Current configuration:".bold().blue());
+    if let Some(profile) = profile {
+        println!("   Profile: {}", profile);
+    }
     println!("   GitLab Token: {:.13}...(Masked)", args.gitlab_token);
     println!("   Instance URL: {}", args.instance_url);
     println!("   Scan type: {:?}", args.scan_type);
+    if let Some(proxy) = &args.http.proxy {
+        println!("   Proxy: {}", proxy);
+    }
+    if let Some(severity) = args.fail_policy.fail_on {
+        println!("   Fail on: {} severity and above", severity);
+    }
+    if let Some(max) = args.fail_policy.max_findings {
+        println!("   Max findings: {}", max);
+    }
+    println!("   Output directory: {}", args.output_dir.display());
+    if let Some(interval) = args.watch {
+        println!(
+            "   Watch: every {} minutes, state in {}",
+            interval.as_secs() / 60,
+            args.watch_state.display()
+        );
+    }
+    if !args.encrypt_to.is_empty() {
+        println!("   Encrypted to: {} recipients", args.encrypt_to.len());
+    }
+    if args.redact {
+        println!("   Redacted traces: enabled");
+    }
+    if args.issues.is_some() {
+        println!("   Issues: enabled");
+    }
+    if !args.verify.is_empty() {
+        let providers: Vec<String> = args.verify.iter().map(|p| p.to_string()).collect();
+        println!(
+            "   Verification: {} ({} requests/s)",
+            providers.join(", "),
+            args.verify_rate
+        );
+    }
+    if args.revoke_live_tokens {
+        println!("   Live Gitlab tokens are revoked");
+    }
+    if !args.notify.is_empty() {
+        println!(
+            "   Notifications: {} webhooks, {} severity and above",
+            args.notify.len(),
+            args.notify_min_severity
+        );
+    }
 
-    let groups = match fetch_groups(&args.gitlab_token, &args.instance_url).await {
-        Ok(groups) => groups,
+    let detector = match Detector::with_rule_packs(&args.rules) {
+        Ok(detector) => detector,
         Err(e) => {
-            println!("Error fetching groups: {:?}", e);
-            return;
+            println!("Error loading detection rules: {}", e);
+            return Exit::Fatal.into();
         }
     };
+    println!("   Detection rules: {}", detector.rules.len());
+    if !detector.allowlists.is_empty() {
+        println!("   Allowlists: {}", detector.allowlists.len());
+    }
 
-    let projects =
-        match fetch_projects_from_groups(&args.gitlab_token, &args.instance_url, &groups).await {
-            Ok(projects) => projects,
+    let mut baseline = match &args.baseline {
+        Some(path) => match Baseline::load_or_default(path, args.update_baseline) {
+            Ok(baseline) => {
+                println!(
+                    "   Baseline: {} ({} accepted findings)",
+                    path.display(),
+                    baseline.findings.len()
+                );
+                Some(baseline)
+            }
             Err(e) => {
-                println!("Error fetching projects: {:?}", e);
-                return;
+                println!("Error loading the baseline: {}", e);
+                return Exit::Fatal.into();
             }
-        };
+        },
+        None => None,
+    };
+
+    let http = match build_client(&args.http) {
+        Ok(http) => http,
+        Err(e) => {
+            println!("Error configuring the HTTP client: {}", e);
+            return Exit::Fatal.into();
+        }
+    };
+
+    match detect_api_root(&http, &args.gitlab_token, &args.instance_url).await {
+        Ok((api_root, version)) => {
+            println!("   API root: {} (Gitlab {})", api_root, version.version);
+            args.instance_url = api_root;
+        }
+        Err(e) => {
+            println!("Error detecting the Gitlab API: {}", e);
+            return Exit::Fatal.into();
+        }
+    };
+
+    let gitlab = GitlabClient::new(http, &args.gitlab_token, &args.instance_url);
+
+    // Revocation goes through the administrator API, better refuse now than after a long scan.
+    if args.revoke_live_tokens {
+        match is_admin(&gitlab).await {
+            Ok(true) => {}
+            Ok(false) => {
+                println!("Error: --revoke-live-tokens requires an administrator token.");
+                return Exit::Fatal.into();
+            }
+            Err(e) => {
+                println!("Error checking the token permissions: {:?}", e);
+                return Exit::Fatal.into();
+            }
+        }
+    }
 
-    match fetch_job_traces_for_projects(&args.gitlab_token, &args.instance_url, &projects).await {
-        Ok(_) => println!("{}", "\nFinished fetching job traces.".blue().bold()),
-        Err(e) => println!("Error fetching job traces: {:?}", e),
+    if args.clean {
+        println!("{}", "Removing earlier runs...".bold().blue());
+        match clean_output_dir(&args.output_dir) {
+            Ok(removed) => println!("{}", format!("   Removed {} runs.", removed).blue()),
+            Err(e) => {
+                println!("Error removing earlier runs: {}", e);
+                return Exit::Fatal.into();
+            }
+        }
+    }
+
+    let Some(interval) = args.watch else {
+        return match scan(&args, &gitlab, &detector, &mut baseline, None).await {
+            Ok(scanned) => {
+                if let Some(run_dir) = scanned.run_dir {
+                    println!(
+                        "{}",
+                        format!("Finished scanning, results saved at {}.", run_dir.display())
+                            .green()
+                            .bold()
+                    );
+                }
+                scanned.exit.into()
+            }
+            Err(e) => {
+                println!("{}", e.red());
+                Exit::Fatal.into()
+            }
+        };
     };
 
+    watch(&args, &gitlab, &detector, &mut baseline, interval)
+        .await
+        .into()
+}
+
+/// The run directory of a scan, if one was kept, and how it ended.
+struct Scanned {
+    run_dir: Option<PathBuf>,
+    exit: Exit,
+}
+
+/// Scans the projects of the instance into a new run directory. With a watch state, only the
+/// jobs finished since the previous poll are fetched and no run is kept when there is none.
+async fn scan(
+    args: &ValidatedArgs,
+    gitlab: &GitlabClient,
+    detector: &Detector,
+    baseline: &mut Option<Baseline>,
+    mut state: Option<&mut WatchState>,
+) -> Result<Scanned, String> {
+    let groups = fetch_groups(gitlab)
+        .await
+        .map_err(|e| format!("Error fetching groups: {:?}", e))?;
+
+    let tree = NamespaceTree::new(groups);
     println!(
         "{}",
-        format!("Finished scanning, results saved at /results.")
-            .green()
+        format!(
+            "Namespace tree has {} groups, {} top-level.",
+            tree.len(),
+            tree.roots().len()
+        )
+        .blue()
+    );
+
+    let group_projects = fetch_projects_from_groups(gitlab, &tree)
+        .await
+        .map_err(|e| format!("Error fetching projects: {:?}", e))?;
+
+    let (other_projects, failed_users) = fetch_projects_outside_groups(gitlab)
+        .await
+        .map_err(|e| format!("Error fetching projects outside groups: {:?}", e))?;
+
+    let projects = merge_projects(vec![group_projects, other_projects]);
+    println!(
+        "{}",
+        format!("Scanning {} unique projects.", projects.len())
+            .blue()
             .bold()
     );
+
+    let started_at = Utc::now();
+    let run_dir = create_run_dir(&args.output_dir, started_at)
+        .map_err(|e| format!("Error creating the run directory: {}", e))?;
+
+    let recipients = parse_recipients(&args.encrypt_to)
+        .map_err(|e| format!("Error parsing encryption recipients: {}", e))?;
+    let writer = ArtifactWriter::new(&run_dir, recipients);
+    let processor = TraceProcessor::new(writer, detector.clone(), args.redact);
+
+    let output = fetch_job_traces_for_projects(
+        gitlab,
+        &projects,
+        &processor,
+        state.as_deref(),
+        &args.job_filter,
+    )
+    .await
+    .map_err(|e| format!("Error fetching job traces: {:?}", e))?;
+    println!("{}", "\nFinished fetching job traces.".blue().bold());
+
+    let mut failures = output.failures + failed_users;
+
+    // A poll without new jobs leaves no empty run behind, its failures are still reported.
+    if let Some(state) = state.as_deref_mut()
+        && output.manifest.entries.is_empty()
+    {
+        std::fs::remove_dir_all(&run_dir)
+            .map_err(|e| format!("Error removing {}: {}", run_dir.display(), e))?;
+        save_state(state, &args.watch_state, &output.polled, started_at);
+        if failures > 0 {
+            println!(
+                "{}",
+                format!(
+                    "{} jobs or steps failed, they are retried at the next poll.",
+                    failures
+                )
+                .red()
+            );
+        }
+        return Ok(Scanned {
+            run_dir: None,
+            exit: args.fail_policy.exit(&[], failures),
+        });
+    }
+
+    let (mut findings, suppressed) =
+        suppress(output.findings, &processor.detector, baseline.as_ref());
+
+    if !args.verify.is_empty() {
+        println!("{}", "Verifying detected secrets...".bold().blue());
+        match build_verifiers(&args.verify, gitlab, &args.verifier_urls).await {
+            Ok(verifiers) => {
+                let limiter = RateLimiter::per_second(args.verify_rate);
+                verify_findings(&verifiers, &mut findings, &limiter).await;
+            }
+            Err(e) => {
+                failures += 1;
+                println!("Error setting up the verifiers: {:?}", e)
+            }
+        }
+    }
+
+    if args.revoke_live_tokens {
+        failures += revoke_live_tokens(gitlab, &mut findings).await;
+    }
+
+    let report = Report {
+        generated_at: Utc::now().to_rfc3339(),
+        instance_url: args.instance_url.to_string(),
+        summary: Summary {
+            projects: projects.len(),
+            jobs: output.manifest.entries.len(),
+            findings: findings.len(),
+            suppressed,
+        },
+        findings,
+    };
+
+    let written = report.write(&processor.writer).and_then(|file| {
+        report
+            .write_formats(
+                &processor.writer,
+                &args.report_formats,
+                &output.manifest.entries,
+            )
+            .map(|_| file)
+    });
+    match written {
+        Ok(file) => println!(
+            "{}",
+            format!(
+                "Found {} secrets in {} traces ({} suppressed), report saved at {}.",
+                report.summary.findings,
+                report.summary.jobs,
+                report.summary.suppressed,
+                run_dir.join(file).display()
+            )
+            .blue()
+            .bold()
+        ),
+        Err(e) => {
+            failures += 1;
+            println!("Error writing report: {}", e)
+        }
+    }
+
+    if let Some(db) = &args.db {
+        let record = RunRecord {
+            started_at: &started_at.to_rfc3339(),
+            run_dir: &run_dir,
+            report: &report,
+            groups: &tree.groups(),
+            projects: &projects,
+            jobs: &output.manifest.entries,
+        };
+        match Store::open(db).and_then(|mut store| store.record_run(&record)) {
+            Ok(run_id) => println!(
+                "{}",
+                format!("Recorded run {} in {}.", run_id, db.display()).blue()
+            ),
+            Err(e) => {
+                failures += 1;
+                println!("Error recording the run: {}", e)
+            }
+        }
+    }
+
+    if let (Some(baseline), Some(path)) = (baseline.as_mut(), &args.baseline)
+        && args.update_baseline
+    {
+        let added = baseline.add(&report.findings);
+        match baseline.write(path) {
+            Ok(()) => println!(
+                "{}",
+                format!(
+                    "Added {} findings to the baseline {}.",
+                    added,
+                    path.display()
+                )
+                .blue()
+            ),
+            Err(e) => {
+                failures += 1;
+                println!("Error updating the baseline: {}", e)
+            }
+        }
+    }
+
+    if !args.notify.is_empty() {
+        // The findings of earlier runs, e.g. last night's scan, were notified with them. They
+        // are kept in a state file of their own, encrypted reports cannot be read back.
+        let path = args.output_dir.join(NOTIFIED_FILE);
+        let mut notified = NotifiedState::load(&path).unwrap_or_else(|e| {
+            println!("{}, every finding is notified.", e);
+            NotifiedState::default()
+        });
+        let findings = notified.new_findings(&report.findings);
+        match sink_client() {
+            Ok(http) => {
+                let failed = notify(
+                    &http,
+                    &args.notify,
+                    &findings,
+                    args.notify_min_severity,
+                    &report.instance_url,
+                )
+                .await;
+                // Findings whose notification failed are sent again by the next run.
+                if failed == 0 {
+                    notified.record(&report.findings);
+                    if let Err(e) = notified.write(&path) {
+                        failures += 1;
+                        println!("Error saving the notified findings: {}", e);
+                    }
+                }
+                failures += failed;
+            }
+            Err(e) => {
+                failures += 1;
+                println!("Error sending notifications: {}", e);
+            }
+        }
+    }
+
+    if let Some(options) = &args.issues {
+        match sync_issues(gitlab, &report.findings, options).await {
+            Ok(failed) => failures += failed,
+            Err(e) => {
+                failures += 1;
+                println!("Error opening issues: {:?}", e);
+            }
+        }
+    }
+
+    if let Some(state) = state {
+        save_state(state, &args.watch_state, &output.polled, started_at);
+    }
+
+    if let Some(reason) = args.fail_policy.check(&report.findings) {
+        println!("{}", format!("Failing the run: {}.", reason).red().bold());
+    }
+    if failures > 0 {
+        println!(
+            "{}",
+            format!(
+                "{} jobs or steps failed, the results are incomplete.",
+                failures
+            )
+            .red()
+        );
+    }
+
+    Ok(Scanned {
+        exit: args.fail_policy.exit(&report.findings, failures),
+        run_dir: Some(run_dir),
+    })
+}
+
+fn save_state(
+    state: &mut WatchState,
+    path: &Path,
+    polled: &BTreeMap<u64, Vec<PolledJob>>,
+    polled_at: DateTime<Utc>,
+) {
+    state.record(polled);
+    state.last_poll = Some(polled_at.to_rfc3339());
+    if let Err(e) = state.write(path) {
+        println!("Error saving the watch state: {}", e);
+    }
+}
+
+/// Polls the instance until interrupted. A failed poll is retried at the next one, the state
+/// only moves forward once the jobs of a poll were reported.
+async fn watch(
+    args: &ValidatedArgs,
+    gitlab: &GitlabClient,
+    detector: &Detector,
+    baseline: &mut Option<Baseline>,
+    interval: Duration,
+) -> Exit {
+    let mut state = match WatchState::load(&args.watch_state) {
+        Ok(state) => state,
+        Err(e) => {
+            println!("Error loading the watch state: {}", e);
+            return Exit::Fatal;
+        }
+    };
+    if let Some(last_poll) = &state.last_poll {
+        println!(
+            "{}",
+            format!(
+                "Resuming the watch of {} projects, last poll at {}.",
+                state.projects.len(),
+                last_poll
+            )
+            .blue()
+        );
+    }
+
+    loop {
+        match scan(args, gitlab, detector, baseline, Some(&mut state)).await {
+            Ok(Scanned {
+                run_dir: Some(run_dir),
+                ..
+            }) => println!(
+                "{}",
+                format!("Finished polling, results saved at {}.", run_dir.display())
+                    .green()
+                    .bold()
+            ),
+            Ok(Scanned {
+                exit: Exit::Success,
+                ..
+            }) => println!("{}", "No new jobs since the previous poll.".blue()),
+            Ok(_) => println!("{}", "No new jobs could be saved from this poll.".yellow()),
+            Err(e) => println!("{}", e.red()),
+        }
+
+        println!(
+            "{}",
+            format!("Next poll in {} minutes.", interval.as_secs() / 60).blue()
+        );
+        tokio::select! {
+            _ = tokio::time::sleep(interval) => {}
+            _ = tokio::signal::ctrl_c() => {
+                println!("{}", "Stopped watching.".green().bold());
+                return Exit::Success;
+            }
+        }
+    }
 }
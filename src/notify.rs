@@ -0,0 +1,393 @@
+use std::collections::BTreeSet;
+use std::path::Path;
+use std::time::Duration;
+
+use colored::Colorize;
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use url::Url;
+
+use crate::detect::{Finding, Severity};
+use crate::verify::Verification;
+
+pub const NOTIFIED_FILE: &str = "notified.json";
+const MAX_LISTED_FINDINGS: usize = 20;
+const ATTEMPTS: u32 = 3;
+const RETRY_DELAY: Duration = Duration::from_secs(2);
+const SINK_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// The payload shape expected by the receiving end of a webhook.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SinkKind {
+    Slack,
+    Teams,
+    Generic,
+}
+
+#[derive(Debug, Clone)]
+pub struct Sink {
+    pub kind: SinkKind,
+    pub url: Url,
+}
+
+pub fn parse_sinks(kind: SinkKind, values: &[String]) -> Result<Vec<Sink>, String> {
+    values
+        .iter()
+        .map(|value| {
+            Url::parse(value)
+                .map(|url| Sink { kind, url })
+                .map_err(|e| format!("Invalid webhook URL '{}': {}", value, e))
+        })
+        .collect()
+}
+
+/// A client of its own for the webhooks: the Gitlab client's CA bundle, client certificate and
+/// proxy are meant for the instance, not for Slack or Teams.
+pub fn sink_client() -> Result<reqwest::Client, String> {
+    reqwest::Client::builder()
+        .timeout(SINK_TIMEOUT)
+        .build()
+        .map_err(|e| format!("Failed to build the webhook client: {}", e))
+}
+
+/// The fingerprints of the findings of earlier runs, which were notified with them. Kept in
+/// clear next to the runs even when reports are encrypted: fingerprints are hashes, not secrets.
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct NotifiedState {
+    pub fingerprints: BTreeSet<String>,
+}
+
+impl NotifiedState {
+    pub fn load(path: &Path) -> Result<Self, String> {
+        if !path.exists() {
+            return Ok(NotifiedState::default());
+        }
+
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+
+        serde_json::from_str(&content).map_err(|e| format!("Invalid {}: {}", path.display(), e))
+    }
+
+    /// Writes the state through a temporary file, an interrupted write never loses it.
+    pub fn write(&self, path: &Path) -> Result<(), String> {
+        let content = serde_json::to_string_pretty(self)
+            .map_err(|e| format!("Failed to serialize the notified findings: {}", e))?;
+        let temporary = path.with_extension("json.tmp");
+
+        std::fs::write(&temporary, content)
+            .and_then(|()| std::fs::rename(&temporary, path))
+            .map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+    }
+
+    /// The findings not notified by an earlier run.
+    pub fn new_findings(&self, findings: &[Finding]) -> Vec<Finding> {
+        findings
+            .iter()
+            .filter(|f| !self.fingerprints.contains(&f.fingerprint))
+            .cloned()
+            .collect()
+    }
+
+    pub fn record(&mut self, findings: &[Finding]) {
+        self.fingerprints
+            .extend(findings.iter().map(|f| f.fingerprint.clone()));
+    }
+}
+
+/// A finding as sent to a webhook, it never holds the secret nor the matched line.
+#[derive(Debug, Serialize)]
+struct NotifiedFinding<'a> {
+    id: &'a str,
+    rule_id: &'a str,
+    description: &'a str,
+    severity: Severity,
+    project_path: &'a str,
+    job_id: u64,
+    job_name: &'a str,
+    job_web_url: &'a str,
+    #[serde(rename = "ref")]
+    git_ref: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    verification: Option<Verification>,
+}
+
+impl<'a> From<&'a Finding> for NotifiedFinding<'a> {
+    fn from(finding: &'a Finding) -> Self {
+        NotifiedFinding {
+            id: &finding.id,
+            rule_id: &finding.rule_id,
+            description: &finding.description,
+            severity: finding.severity,
+            project_path: &finding.project_path,
+            job_id: finding.job_id,
+            job_name: &finding.job_name,
+            job_web_url: &finding.job_web_url,
+            git_ref: &finding.git_ref,
+            verification: finding.verification,
+        }
+    }
+}
+
+fn title(findings: &[&Finding], instance_url: &str) -> String {
+    format!(
+        "Gitlab auditor: {} new secrets found in CI job logs on {}",
+        findings.len(),
+        instance_url
+    )
+}
+
+/// One line per finding with a Markdown link to the job, limited to the first ones.
+fn finding_lines(findings: &[&Finding], link: impl Fn(&str, &str) -> String) -> Vec<String> {
+    let mut lines: Vec<String> = findings
+        .iter()
+        .take(MAX_LISTED_FINDINGS)
+        .map(|f| {
+            format!(
+                "[{}] {} in {}, {}",
+                f.severity,
+                f.rule_id,
+                f.project_path,
+                link(&format!("job {} #{}", f.job_name, f.job_id), &f.job_web_url)
+            )
+        })
+        .collect();
+
+    if findings.len() > MAX_LISTED_FINDINGS {
+        lines.push(format!(
+            "... and {} more, see the report.",
+            findings.len() - MAX_LISTED_FINDINGS
+        ));
+    }
+
+    lines
+}
+
+pub fn payload(kind: SinkKind, findings: &[&Finding], instance_url: &str) -> Value {
+    let title = title(findings, instance_url);
+
+    match kind {
+        SinkKind::Slack => {
+            let lines = finding_lines(findings, |text, url| format!("<{}|{}>", url, text));
+            json!({
+                "text": title,
+                "blocks": [
+                    { "type": "header", "text": { "type": "plain_text", "text": title } },
+                    { "type": "section", "text": { "type": "mrkdwn", "text": lines.join("\n") } },
+                ],
+            })
+        }
+        SinkKind::Teams => {
+            let lines = finding_lines(findings, |text, url| format!("[{}]({})", text, url));
+            json!({
+                "type": "message",
+                "attachments": [{
+                    "contentType": "application/vnd.microsoft.card.adaptive",
+                    "content": {
+                        "$schema": "http://adaptivecards.io/schemas/adaptive-card.json",
+                        "type": "AdaptiveCard",
+                        "version": "1.4",
+                        "body": [
+                            { "type": "TextBlock", "text": title, "weight": "Bolder", "wrap": true },
+                            { "type": "TextBlock", "text": lines.join("\n\n"), "wrap": true },
+                        ],
+                    },
+                }],
+            })
+        }
+        SinkKind::Generic => {
+            let notified: Vec<NotifiedFinding> = findings.iter().map(|f| (*f).into()).collect();
+            json!({
+                "source": "gitlab-auditor",
+                "title": title,
+                "instance_url": instance_url,
+                "findings": notified,
+            })
+        }
+    }
+}
+
+/// Posts the payload, retrying on network errors, rate limits and server errors.
+pub async fn send(
+    http: &reqwest::Client,
+    url: &Url,
+    payload: &Value,
+    retry_delay: Duration,
+) -> Result<(), String> {
+    let mut error = String::new();
+
+    for attempt in 1..=ATTEMPTS {
+        if attempt > 1 {
+            tokio::time::sleep(retry_delay * (attempt - 1)).await;
+        }
+
+        match http.post(url.clone()).json(payload).send().await {
+            Ok(response) if response.status().is_success() => return Ok(()),
+            Ok(response)
+                if response.status().is_server_error()
+                    || response.status() == reqwest::StatusCode::TOO_MANY_REQUESTS =>
+            {
+                error = format!("HTTP {}", response.status());
+            }
+            Ok(response) => return Err(format!("HTTP {}", response.status())),
+            // Webhook URLs embed a secret, they are kept out of the error messages.
+            Err(e) => error = e.without_url().to_string(),
+        }
+    }
+
+    Err(format!("{} after {} attempts", error, ATTEMPTS))
+}
+
+/// Notifies every sink of the findings at or above the minimum severity, returns how many sinks
+/// could not be notified.
+pub async fn notify(
+    http: &reqwest::Client,
+    sinks: &[Sink],
+    findings: &[Finding],
+    min_severity: Severity,
+    instance_url: &str,
+) -> usize {
+    let findings: Vec<&Finding> = findings
+        .iter()
+        .filter(|f| f.severity >= min_severity)
+        .collect();
+
+    if sinks.is_empty() || findings.is_empty() {
+        return 0;
+    }
+
+    let mut failed = 0;
+    println!("{}", "Sending notifications...".bold().blue());
+    for sink in sinks {
+        let payload = payload(sink.kind, &findings, instance_url);
+        // The host is enough to tell sinks apart, webhook paths usually embed a secret.
+        let host = sink.url.host_str().unwrap_or_default();
+
+        match send(http, &sink.url, &payload, RETRY_DELAY).await {
+            Ok(()) => println!(
+                "{}",
+                format!("   Notified {:?} webhook on {}.", sink.kind, host).blue()
+            ),
+            Err(e) => {
+                failed += 1;
+                println!(
+                    "{}",
+                    format!(
+                        "   Failed to notify {:?} webhook on {}: {}",
+                        sink.kind, host, e
+                    )
+                    .red()
+                )
+            }
+        }
+    }
+
+    failed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::detect::test_finding;
+    use wiremock::matchers::method;
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    #[test]
+    fn test_payloads_never_hold_the_secret() {
+        let finding = test_finding(42, 1001, 3);
+
+        for kind in [SinkKind::Slack, SinkKind::Teams, SinkKind::Generic] {
+            let body = payload(kind, &[&finding], "https://gitlab.example.com").to_string();
+
+            assert!(!body.contains(&finding.secret));
+            assert!(!body.contains(&finding.redacted_line));
+            assert!(body.contains("https://gitlab.com/group/project-42/-/jobs/1001"));
+            assert!(body.contains("group/project-42"));
+        }
+    }
+
+    #[test]
+    fn test_findings_of_earlier_runs_are_not_notified_again() {
+        let path = std::env::temp_dir().join(format!(
+            "gitlab_auditor_notified_{}.json",
+            std::process::id()
+        ));
+        let mut state = NotifiedState::load(&path).unwrap();
+        state.record(&[test_finding(1, 10, 3)]);
+        state.write(&path).unwrap();
+
+        let state = NotifiedState::load(&path).unwrap();
+        let content = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let new = state.new_findings(&[test_finding(1, 10, 3), test_finding(1, 11, 3)]);
+
+        assert_eq!(new.len(), 1);
+        assert_eq!(new[0].job_id, 11);
+        assert!(!content.contains(&test_finding(1, 10, 3).secret));
+    }
+
+    #[test]
+    fn test_long_lists_are_truncated() {
+        let findings: Vec<Finding> = (0..25).map(|job| test_finding(1, job, 1)).collect();
+        let refs: Vec<&Finding> = findings.iter().collect();
+
+        let lines = finding_lines(&refs, |text, _| text.to_string());
+
+        assert_eq!(lines.len(), MAX_LISTED_FINDINGS + 1);
+        assert_eq!(
+            lines[0],
+            "[high] gitlab-pat in group/project-1, job build #0"
+        );
+        assert_eq!(
+            lines[MAX_LISTED_FINDINGS],
+            "... and 5 more, see the report."
+        );
+    }
+
+    #[tokio::test]
+    async fn test_server_errors_are_retried() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(503))
+            .up_to_n_times(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(200))
+            .mount(&server)
+            .await;
+
+        let url = Url::parse(&server.uri()).unwrap();
+        let result = send(
+            &reqwest::Client::new(),
+            &url,
+            &json!({}),
+            Duration::from_millis(1),
+        )
+        .await;
+
+        assert_eq!(result, Ok(()));
+        assert_eq!(server.received_requests().await.unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_client_errors_are_not_retried() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(404))
+            .mount(&server)
+            .await;
+
+        let url = Url::parse(&server.uri()).unwrap();
+        let result = send(
+            &reqwest::Client::new(),
+            &url,
+            &json!({}),
+            Duration::from_millis(1),
+        )
+        .await;
+
+        assert!(result.is_err());
+        assert_eq!(server.received_requests().await.unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,306 @@
+use std::path::{Path, PathBuf};
+
+use age::x25519;
+use chrono::{DateTime, NaiveDateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::crypto::{ENCRYPTED_EXTENSION, encrypt, read_artifact};
+use crate::scans::shared::{Job, Project};
+
+pub const DEFAULT_OUTPUT_DIR: &str = "results";
+pub const LOG_TRACES_DIR: &str = "log_traces";
+pub const MANIFEST_FILE: &str = "manifest.json";
+const RUN_ID_FORMAT: &str = "%Y%m%dT%H%M%SZ";
+
+pub fn run_id(now: DateTime<Utc>) -> String {
+    now.format(RUN_ID_FORMAT).to_string()
+}
+
+/// Run directories are named after their UTC start time, with a `-N` suffix
+/// when several runs start within the same second.
+pub fn is_run_dir_name(name: &str) -> bool {
+    let timestamp = name.split_once('-').map_or(name, |(timestamp, suffix)| {
+        if suffix.chars().all(|c| c.is_ascii_digit()) {
+            timestamp
+        } else {
+            name
+        }
+    });
+
+    NaiveDateTime::parse_from_str(timestamp, RUN_ID_FORMAT).is_ok()
+}
+
+/// Removes the run directories of earlier scans, anything else in the output directory is kept.
+pub fn clean_output_dir(output_dir: &Path) -> Result<usize, String> {
+    if !output_dir.exists() {
+        return Ok(0);
+    }
+
+    let entries = std::fs::read_dir(output_dir)
+        .map_err(|e| format!("Failed to read {}: {}", output_dir.display(), e))?;
+    let mut removed = 0;
+
+    for entry in entries.flatten() {
+        let is_run_dir = entry.file_type().is_ok_and(|t| t.is_dir())
+            && entry.file_name().to_str().is_some_and(is_run_dir_name);
+
+        if is_run_dir {
+            std::fs::remove_dir_all(entry.path())
+                .map_err(|e| format!("Failed to remove {}: {}", entry.path().display(), e))?;
+            removed += 1;
+        }
+    }
+
+    Ok(removed)
+}
+
+/// Creates `<output_dir>/<run id>` for a new scan.
+pub fn create_run_dir(output_dir: &Path, now: DateTime<Utc>) -> Result<PathBuf, String> {
+    std::fs::create_dir_all(output_dir)
+        .map_err(|e| format!("Failed to create {}: {}", output_dir.display(), e))?;
+
+    let run_id = run_id(now);
+    let mut run_dir = output_dir.join(&run_id);
+    let mut suffix = 1;
+
+    // Creating the directory is the existence check, two runs starting together never share one.
+    loop {
+        match std::fs::create_dir(&run_dir) {
+            Ok(()) => return Ok(run_dir),
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {
+                run_dir = output_dir.join(format!("{}-{}", run_id, suffix));
+                suffix += 1;
+            }
+            Err(e) => return Err(format!("Failed to create {}: {}", run_dir.display(), e)),
+        }
+    }
+}
+
+/// Replaces every character that is not safe in a file name on all platforms.
+pub fn sanitize_path_component(value: &str) -> String {
+    let sanitized: String = value
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+
+    // Leading dots would create hidden files or `.`/`..` components.
+    sanitized.trim_start_matches('.').to_string()
+}
+
+/// Directory of a project traces, relative to the run directory, e.g.
+/// `log_traces/group__subgroup__api-42`. The project ID keeps two projects
+/// apart even when their sanitized paths are identical.
+pub fn project_trace_dir(project: &Project) -> String {
+    let path = project
+        .path_with_namespace
+        .split('/')
+        .map(sanitize_path_component)
+        .collect::<Vec<_>>()
+        .join("__");
+
+    format!("{}/{}-{}", LOG_TRACES_DIR, path, project.id)
+}
+
+pub fn job_trace_file(project: &Project, job: &Job) -> String {
+    format!("{}/{}.txt", project_trace_dir(project), job.id)
+}
+
+/// Writes the files of a run. When recipients are configured every file is encrypted
+/// in memory first and gets the `.age` extension, so no plaintext reaches the disk.
+#[derive(Clone)]
+pub struct ArtifactWriter {
+    pub run_dir: PathBuf,
+    recipients: Vec<x25519::Recipient>,
+}
+
+impl ArtifactWriter {
+    pub fn new(run_dir: &Path, recipients: Vec<x25519::Recipient>) -> Self {
+        ArtifactWriter {
+            run_dir: run_dir.to_path_buf(),
+            recipients,
+        }
+    }
+
+    /// A writer encrypting to the same recipients into another run directory.
+    pub fn with_run_dir(&self, run_dir: &Path) -> Self {
+        ArtifactWriter {
+            run_dir: run_dir.to_path_buf(),
+            recipients: self.recipients.clone(),
+        }
+    }
+
+    pub fn is_encrypted(&self) -> bool {
+        !self.recipients.is_empty()
+    }
+
+    /// Writes a file relative to the run directory and returns its actual relative path.
+    pub fn write(&self, file: &str, content: &[u8]) -> Result<String, String> {
+        let (file, content) = if self.is_encrypted() {
+            (
+                format!("{}.{}", file, ENCRYPTED_EXTENSION),
+                encrypt(&self.recipients, content)?,
+            )
+        } else {
+            (file.to_string(), content.to_vec())
+        };
+
+        let path = self.run_dir.join(&file);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+        }
+
+        std::fs::write(&path, content)
+            .map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+
+        Ok(file)
+    }
+}
+
+/// Finds a run file, encrypted or not.
+pub fn find_artifact(run_dir: &Path, file: &str) -> Option<PathBuf> {
+    [
+        run_dir.join(file),
+        run_dir.join(format!("{}.{}", file, ENCRYPTED_EXTENSION)),
+    ]
+    .into_iter()
+    .find(|path| path.exists())
+}
+
+/// Maps every written trace file back to the Gitlab job it was fetched from.
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct Manifest {
+    pub entries: Vec<ManifestEntry>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ManifestEntry {
+    pub file: String,
+    pub project_id: u64,
+    pub project_path: String,
+    pub pipeline_id: u64,
+    pub job_id: u64,
+    pub job_name: String,
+    pub job_status: String,
+    #[serde(rename = "ref")]
+    pub git_ref: String,
+    pub commit_sha: String,
+    pub job_web_url: String,
+    pub created_at: String,
+    pub started_at: Option<String>,
+    pub finished_at: Option<String>,
+    // Sidecar mapping the placeholders of a redacted trace, see `--redact`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub redactions: Option<String>,
+}
+
+impl ManifestEntry {
+    pub fn new(file: &str, project: &Project, job: &Job) -> Self {
+        ManifestEntry {
+            file: file.to_string(),
+            project_id: project.id,
+            project_path: project.path_with_namespace.clone(),
+            pipeline_id: job.pipeline.id,
+            job_id: job.id,
+            job_name: job.name.clone(),
+            job_status: job.status.clone(),
+            git_ref: job.git_ref.clone(),
+            commit_sha: job.pipeline.sha.clone(),
+            job_web_url: job.web_url.clone(),
+            created_at: job.created_at.clone(),
+            started_at: job.started_at.clone(),
+            finished_at: job.finished_at.clone(),
+            redactions: None,
+        }
+    }
+}
+
+impl Manifest {
+    pub fn write(&self, writer: &ArtifactWriter) -> Result<String, String> {
+        let content = serde_json::to_string_pretty(self)
+            .map_err(|e| format!("Failed to serialize manifest: {}", e))?;
+
+        writer.write(MANIFEST_FILE, content.as_bytes())
+    }
+
+    pub fn read(run_dir: &Path, identities: &[Box<dyn age::Identity>]) -> Result<Self, String> {
+        let path = find_artifact(run_dir, MANIFEST_FILE)
+            .ok_or_else(|| format!("No {} found in {}", MANIFEST_FILE, run_dir.display()))?;
+        let content = read_artifact(&path, identities)?;
+
+        serde_json::from_slice(&content)
+            .map_err(|e| format!("Invalid manifest {}: {}", path.display(), e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn project(id: u64, name: &str, path_with_namespace: &str) -> Project {
+        Project {
+            id,
+            name: name.to_string(),
+            path_with_namespace: path_with_namespace.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_run_dir_names() {
+        let now = DateTime::parse_from_rfc3339("2025-03-14T09:26:53Z")
+            .unwrap()
+            .with_timezone(&Utc);
+
+        assert_eq!(run_id(now), "20250314T092653Z");
+        assert!(is_run_dir_name("20250314T092653Z"));
+        assert!(is_run_dir_name("20250314T092653Z-2"));
+        assert!(!is_run_dir_name("log_traces"));
+        assert!(!is_run_dir_name("20250314T092653Z-old"));
+    }
+
+    #[test]
+    fn test_sanitize_path_component() {
+        assert_eq!(sanitize_path_component("my api: v2?"), "my_api__v2_");
+        assert_eq!(sanitize_path_component("..secret"), "secret");
+        assert_eq!(sanitize_path_component("back\\slash"), "back_slash");
+    }
+
+    #[test]
+    fn test_same_name_in_different_groups() {
+        let first = project(1, "api", "team-a/api");
+        let second = project(2, "api", "team-b/api");
+
+        assert_eq!(project_trace_dir(&first), "log_traces/team-a__api-1");
+        assert_eq!(project_trace_dir(&second), "log_traces/team-b__api-2");
+    }
+
+    #[test]
+    fn test_identical_sanitized_paths() {
+        let first = project(1, "a b", "group/a b");
+        let second = project(2, "a_b", "group/a_b");
+
+        assert_ne!(project_trace_dir(&first), project_trace_dir(&second));
+    }
+
+    #[test]
+    fn test_runs_of_the_same_second_get_a_suffix() {
+        let output_dir =
+            std::env::temp_dir().join(format!("gitlab_auditor_same_second_{}", std::process::id()));
+        let now = DateTime::parse_from_rfc3339("2025-03-14T09:26:53Z")
+            .unwrap()
+            .with_timezone(&Utc);
+
+        let first = create_run_dir(&output_dir, now);
+        let second = create_run_dir(&output_dir, now);
+        std::fs::remove_dir_all(&output_dir).unwrap();
+
+        assert_eq!(first.unwrap(), output_dir.join("20250314T092653Z"));
+        assert_eq!(second.unwrap(), output_dir.join("20250314T092653Z-1"));
+    }
+}
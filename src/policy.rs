@@ -0,0 +1,129 @@
+use std::process::ExitCode;
+
+use crate::detect::{Finding, Severity};
+
+/// How a run ended, each outcome has its own exit code so that CI jobs and cron can react.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Exit {
+    /// The scan completed and the findings are within the policy.
+    Success,
+    /// The findings break `--fail-on` or `--max-findings`.
+    Findings,
+    /// Some traces could not be fetched or some results could not be saved or sent.
+    Partial,
+    /// Invalid configuration, authentication failure or any error stopping the run.
+    Fatal,
+}
+
+impl Exit {
+    pub fn code(&self) -> u8 {
+        match self {
+            Exit::Success => 0,
+            Exit::Findings => 1,
+            Exit::Partial => 2,
+            Exit::Fatal => 3,
+        }
+    }
+}
+
+impl From<Exit> for ExitCode {
+    fn from(exit: Exit) -> Self {
+        ExitCode::from(exit.code())
+    }
+}
+
+/// The findings a run tolerates before failing, nothing fails it by default.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FailPolicy {
+    pub fail_on: Option<Severity>,
+    pub max_findings: Option<usize>,
+}
+
+impl FailPolicy {
+    /// Explains why the reported findings break the policy, if they do.
+    pub fn check(&self, findings: &[Finding]) -> Option<String> {
+        if let Some(severity) = self.fail_on {
+            let count = findings.iter().filter(|f| f.severity >= severity).count();
+            if count > 0 {
+                return Some(format!(
+                    "{} findings at or above {} severity (--fail-on {})",
+                    count, severity, severity
+                ));
+            }
+        }
+
+        if let Some(max) = self.max_findings
+            && findings.len() > max
+        {
+            return Some(format!(
+                "{} findings, more than the {} allowed (--max-findings)",
+                findings.len(),
+                max
+            ));
+        }
+
+        None
+    }
+
+    /// Findings breaking the policy win over failures: the leak is there even if the scan is
+    /// incomplete.
+    pub fn exit(&self, findings: &[Finding], failures: usize) -> Exit {
+        if self.check(findings).is_some() {
+            Exit::Findings
+        } else if failures > 0 {
+            Exit::Partial
+        } else {
+            Exit::Success
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::detect::test_finding;
+
+    #[test]
+    fn test_default_policy_never_fails_on_findings() {
+        let findings = vec![test_finding(1, 10, 1)];
+
+        assert_eq!(FailPolicy::default().exit(&findings, 0), Exit::Success);
+        assert_eq!(FailPolicy::default().exit(&findings, 1), Exit::Partial);
+    }
+
+    #[test]
+    fn test_fail_on_severity() {
+        let mut findings = vec![test_finding(1, 10, 1)];
+        let policy = FailPolicy {
+            fail_on: Some(Severity::Critical),
+            ..Default::default()
+        };
+        assert_eq!(policy.exit(&findings, 0), Exit::Success);
+
+        findings[0].severity = Severity::Critical;
+        assert_eq!(policy.exit(&findings, 2), Exit::Findings);
+        assert_eq!(
+            policy.check(&findings).unwrap(),
+            "1 findings at or above critical severity (--fail-on critical)"
+        );
+    }
+
+    #[test]
+    fn test_max_findings() {
+        let findings = vec![test_finding(1, 10, 1), test_finding(1, 10, 2)];
+        let policy = |max| FailPolicy {
+            max_findings: Some(max),
+            ..Default::default()
+        };
+
+        assert_eq!(policy(2).exit(&findings, 0), Exit::Success);
+        assert_eq!(policy(1).exit(&findings, 0), Exit::Findings);
+        assert_eq!(policy(0).exit(&[], 0), Exit::Success);
+    }
+
+    #[test]
+    fn test_exit_codes_are_distinct() {
+        let codes = [Exit::Success, Exit::Findings, Exit::Partial, Exit::Fatal].map(|e| e.code());
+        assert_eq!(codes, [0, 1, 2, 3]);
+    }
+}
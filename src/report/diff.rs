@@ -0,0 +1,196 @@
+use std::collections::HashSet;
+
+use serde::{Deserialize, Serialize};
+
+use crate::detect::Finding;
+use crate::report::junit::{ScannedJob, render_junit};
+use crate::report::markdown::render_diff_markdown;
+use crate::report::{Report, ReportFormat};
+
+/// Where a report of the comparison comes from.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct DiffSource {
+    pub generated_at: String,
+    pub instance_url: String,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct DiffSummary {
+    pub new: usize,
+    pub still_present: usize,
+    pub resolved: usize,
+    #[serde(default)]
+    pub not_rescanned: usize,
+}
+
+/// Findings of two runs matched by fingerprint. Resolved findings are the ones of the old run
+/// missing from the new one although their job was scanned again, e.g. the log was erased. The
+/// others, whose job the new run did not scan, are not rescanned rather than resolved.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ReportDiff {
+    pub old: DiffSource,
+    pub new: DiffSource,
+    pub summary: DiffSummary,
+    pub new_findings: Vec<Finding>,
+    pub still_present: Vec<Finding>,
+    pub resolved: Vec<Finding>,
+    #[serde(default)]
+    pub not_rescanned: Vec<Finding>,
+}
+
+fn source(report: &Report) -> DiffSource {
+    DiffSource {
+        generated_at: report.generated_at.clone(),
+        instance_url: report.instance_url.clone(),
+    }
+}
+
+/// Compares two reports, given the jobs scanned by the new run from its manifest.
+pub fn diff_reports(old: &Report, new: &Report, rescanned_jobs: &HashSet<u64>) -> ReportDiff {
+    let old_fingerprints: HashSet<&str> = old
+        .findings
+        .iter()
+        .map(|f| f.fingerprint.as_str())
+        .collect();
+    let new_fingerprints: HashSet<&str> = new
+        .findings
+        .iter()
+        .map(|f| f.fingerprint.as_str())
+        .collect();
+
+    // The new run is the reference for the findings in both, e.g. for their verification.
+    let (still_present, new_findings): (Vec<Finding>, Vec<Finding>) = new
+        .findings
+        .iter()
+        .cloned()
+        .partition(|f| old_fingerprints.contains(f.fingerprint.as_str()));
+    let (resolved, not_rescanned): (Vec<Finding>, Vec<Finding>) = old
+        .findings
+        .iter()
+        .filter(|f| !new_fingerprints.contains(f.fingerprint.as_str()))
+        .cloned()
+        .partition(|f| rescanned_jobs.contains(&f.job_id));
+
+    ReportDiff {
+        old: source(old),
+        new: source(new),
+        summary: DiffSummary {
+            new: new_findings.len(),
+            still_present: still_present.len(),
+            resolved: resolved.len(),
+            not_rescanned: not_rescanned.len(),
+        },
+        new_findings,
+        still_present,
+        resolved,
+        not_rescanned,
+    }
+}
+
+impl ReportDiff {
+    pub fn render(&self, format: ReportFormat) -> Result<String, String> {
+        match format {
+            ReportFormat::Json => serde_json::to_string_pretty(self)
+                .map_err(|e| format!("Failed to serialize the diff: {}", e)),
+            // Only the new findings fail, the jobs of the other ones are passing test cases. The
+            // jobs not rescanned are not part of the new run.
+            ReportFormat::Junit => {
+                let jobs: Vec<ScannedJob> = self
+                    .still_present
+                    .iter()
+                    .chain(&self.resolved)
+                    .map(ScannedJob::from)
+                    .collect();
+                let new_findings: Vec<&Finding> = self.new_findings.iter().collect();
+
+                Ok(render_junit(&jobs, &new_findings, &self.new.generated_at))
+            }
+            ReportFormat::Markdown => Ok(render_diff_markdown(
+                &self.new_findings,
+                &self.still_present,
+                &self.resolved,
+                &self.not_rescanned,
+                (&self.old.generated_at, &self.new.generated_at),
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::detect::test_finding;
+
+    fn report(findings: Vec<Finding>) -> Report {
+        Report {
+            findings,
+            ..Default::default()
+        }
+    }
+
+    fn jobs(ids: &[u64]) -> HashSet<u64> {
+        ids.iter().copied().collect()
+    }
+
+    #[test]
+    fn test_findings_are_matched_by_fingerprint() {
+        let old = report(vec![test_finding(1, 10, 1), test_finding(1, 10, 2)]);
+        let mut moved = test_finding(1, 10, 1);
+        moved.file = "log_traces/renamed-group__project-1-1/10.txt".to_string();
+        let new = report(vec![moved, test_finding(2, 20, 1)]);
+
+        let diff = diff_reports(&old, &new, &jobs(&[10, 20]));
+
+        assert_eq!(diff.summary.new, 1);
+        assert_eq!(diff.summary.still_present, 1);
+        assert_eq!(diff.summary.resolved, 1);
+        assert_eq!(diff.new_findings[0].job_id, 20);
+        assert_eq!(
+            diff.still_present[0].file,
+            "log_traces/renamed-group__project-1-1/10.txt"
+        );
+        assert_eq!(diff.resolved[0].line, 2);
+    }
+
+    #[test]
+    fn test_junit_diff_fails_on_new_findings_only() {
+        let old = report(vec![test_finding(1, 10, 1)]);
+        let new = report(vec![test_finding(1, 10, 1), test_finding(2, 20, 1)]);
+
+        let xml = diff_reports(&old, &new, &jobs(&[10, 20]))
+            .render(ReportFormat::Junit)
+            .unwrap();
+
+        assert!(xml.contains("<testsuites name=\"gitlab-auditor\" tests=\"2\" failures=\"1\">"));
+        assert!(xml.contains("<testsuite name=\"group/project-2\" tests=\"1\" failures=\"1\""));
+    }
+
+    #[test]
+    fn test_markdown_diff_lists_each_category() {
+        let old = report(vec![test_finding(1, 10, 1), test_finding(1, 10, 2)]);
+        let new = report(vec![test_finding(1, 10, 1), test_finding(2, 20, 1)]);
+
+        let markdown = diff_reports(&old, &new, &jobs(&[10, 20]))
+            .render(ReportFormat::Markdown)
+            .unwrap();
+
+        assert!(markdown.contains(
+            "| New | 1 |\n| Still present | 1 |\n| Resolved | 1 |\n| Not rescanned | 0 |"
+        ));
+        assert!(markdown.contains("## New\n\n### group/project\\-2\n"));
+    }
+
+    #[test]
+    fn test_findings_of_jobs_not_rescanned_are_not_resolved() {
+        let old = report(vec![test_finding(1, 10, 1), test_finding(2, 20, 1)]);
+        let new = report(Vec::new());
+
+        // The new run only scanned job 10, e.g. with --since.
+        let diff = diff_reports(&old, &new, &jobs(&[10]));
+
+        assert_eq!(diff.summary.resolved, 1);
+        assert_eq!(diff.summary.not_rescanned, 1);
+        assert_eq!(diff.resolved[0].job_id, 10);
+        assert_eq!(diff.not_rescanned[0].job_id, 20);
+    }
+}
@@ -0,0 +1,209 @@
+use std::path::Path;
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+use crate::detect::{Finding, Severity};
+
+pub const SECRET_DETECTION_REPORT_FILE: &str = "gl-secret-detection-report.json";
+const SCHEMA_VERSION: &str = "15.2.1";
+// The security report schema expects UTC times without offset.
+const TIME_FORMAT: &str = "%Y-%m-%dT%H:%M:%S";
+
+/// A Gitlab secret detection report, shown in the merge request security widget when uploaded
+/// as `artifacts:reports:secret_detection`.
+/// Schema: https://gitlab.com/gitlab-org/security-products/security-report-schemas
+#[derive(Debug, Serialize)]
+pub struct SecurityReport {
+    pub version: &'static str,
+    pub vulnerabilities: Vec<Vulnerability>,
+    pub scan: Scan,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Vulnerability {
+    pub id: String,
+    pub name: String,
+    pub description: String,
+    pub severity: &'static str,
+    pub solution: &'static str,
+    pub identifiers: Vec<Identifier>,
+    pub links: Vec<Link>,
+    pub location: Location,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Identifier {
+    #[serde(rename = "type")]
+    pub kind: &'static str,
+    pub name: String,
+    pub value: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Link {
+    pub name: String,
+    pub url: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Location {
+    pub file: String,
+    pub start_line: usize,
+    pub commit: Commit,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Commit {
+    pub sha: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Scan {
+    pub analyzer: Tool,
+    pub scanner: Tool,
+    #[serde(rename = "type")]
+    pub kind: &'static str,
+    pub start_time: String,
+    pub end_time: String,
+    pub status: &'static str,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Tool {
+    pub id: &'static str,
+    pub name: &'static str,
+    pub version: &'static str,
+    pub vendor: Vendor,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Vendor {
+    pub name: &'static str,
+}
+
+fn tool() -> Tool {
+    Tool {
+        id: "gitlab_auditor",
+        name: "Gitlab auditor",
+        version: env!("CARGO_PKG_VERSION"),
+        vendor: Vendor {
+            name: "Gitlab auditor",
+        },
+    }
+}
+
+fn severity(severity: Severity) -> &'static str {
+    match severity {
+        Severity::Low => "Low",
+        Severity::Medium => "Medium",
+        Severity::High => "High",
+        Severity::Critical => "Critical",
+    }
+}
+
+impl From<&Finding> for Vulnerability {
+    fn from(finding: &Finding) -> Self {
+        Vulnerability {
+            id: finding.fingerprint.clone(),
+            name: finding.description.clone(),
+            // The secret stays out of the report, it is readable by everyone seeing the pipeline.
+            description: format!(
+                "A {} was printed in the log of job {} #{}, line {}.",
+                finding.rule_id, finding.job_name, finding.job_id, finding.line
+            ),
+            severity: severity(finding.severity),
+            solution: "Revoke the secret, erase the job log and keep the secret out of the job output, e.g. with a masked variable.",
+            identifiers: vec![Identifier {
+                kind: "gitlab_auditor_rule_id",
+                name: format!("Gitlab auditor rule {}", finding.rule_id),
+                value: finding.rule_id.clone(),
+            }],
+            links: vec![Link {
+                name: format!("Job {} #{}", finding.job_name, finding.job_id),
+                url: finding.job_web_url.clone(),
+            }],
+            location: Location {
+                file: format!("jobs/{}/trace", finding.job_id),
+                start_line: finding.line,
+                commit: Commit {
+                    sha: finding.commit_sha.clone(),
+                },
+            },
+        }
+    }
+}
+
+impl SecurityReport {
+    /// The scan is reported as a failure when some job logs could not be fetched.
+    pub fn new(
+        findings: &[Finding],
+        started_at: DateTime<Utc>,
+        ended_at: DateTime<Utc>,
+        failed_jobs: usize,
+    ) -> Self {
+        SecurityReport {
+            version: SCHEMA_VERSION,
+            vulnerabilities: findings.iter().map(Vulnerability::from).collect(),
+            scan: Scan {
+                analyzer: tool(),
+                scanner: tool(),
+                kind: "secret_detection",
+                start_time: started_at.format(TIME_FORMAT).to_string(),
+                end_time: ended_at.format(TIME_FORMAT).to_string(),
+                status: if failed_jobs > 0 {
+                    "failure"
+                } else {
+                    "success"
+                },
+            },
+        }
+    }
+
+    pub fn write(&self, path: &Path) -> Result<(), String> {
+        let content = serde_json::to_string_pretty(self)
+            .map_err(|e| format!("Failed to serialize security report: {}", e))?;
+
+        std::fs::write(path, content)
+            .map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::detect::test_finding;
+
+    #[test]
+    fn test_security_report_follows_the_schema_without_secrets() {
+        let finding = test_finding(1, 10, 3);
+        let now = DateTime::parse_from_rfc3339("2025-03-14T09:26:53Z")
+            .unwrap()
+            .with_timezone(&Utc);
+
+        let report = SecurityReport::new(std::slice::from_ref(&finding), now, now, 0);
+        let value = serde_json::to_value(&report).unwrap();
+        let body = value.to_string();
+
+        assert_eq!(value["scan"]["type"], "secret_detection");
+        assert_eq!(value["scan"]["status"], "success");
+        assert_eq!(value["scan"]["start_time"], "2025-03-14T09:26:53");
+        assert_eq!(value["vulnerabilities"][0]["severity"], "High");
+        assert_eq!(value["vulnerabilities"][0]["location"]["start_line"], 3);
+        assert_eq!(
+            value["vulnerabilities"][0]["location"]["commit"]["sha"],
+            "abc123"
+        );
+        assert!(!body.contains(&finding.secret));
+        assert!(!body.contains(&finding.redacted_line));
+    }
+
+    #[test]
+    fn test_failed_jobs_fail_the_scan() {
+        let now = Utc::now();
+
+        let report = SecurityReport::new(&[], now, now, 2);
+
+        assert_eq!(report.scan.status, "failure");
+    }
+}
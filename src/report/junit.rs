@@ -0,0 +1,192 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+use crate::detect::Finding;
+use crate::output::ManifestEntry;
+
+/// A scanned job, one test case of the report.
+#[derive(Debug, Clone, Copy)]
+pub struct ScannedJob<'a> {
+    pub project_path: &'a str,
+    pub job_id: u64,
+    pub job_name: &'a str,
+    pub web_url: &'a str,
+    pub git_ref: &'a str,
+    pub commit_sha: &'a str,
+}
+
+impl<'a> From<&'a ManifestEntry> for ScannedJob<'a> {
+    fn from(entry: &'a ManifestEntry) -> Self {
+        ScannedJob {
+            project_path: &entry.project_path,
+            job_id: entry.job_id,
+            job_name: &entry.job_name,
+            web_url: &entry.job_web_url,
+            git_ref: &entry.git_ref,
+            commit_sha: &entry.commit_sha,
+        }
+    }
+}
+
+impl<'a> From<&'a Finding> for ScannedJob<'a> {
+    fn from(finding: &'a Finding) -> Self {
+        ScannedJob {
+            project_path: &finding.project_path,
+            job_id: finding.job_id,
+            job_name: &finding.job_name,
+            web_url: &finding.job_web_url,
+            git_ref: &finding.git_ref,
+            commit_sha: &finding.commit_sha,
+        }
+    }
+}
+
+/// Escapes text for XML attributes and content. Characters XML 1.0 does not allow, such as the
+/// control characters some job logs are full of, are dropped.
+pub fn escape_xml(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+
+    for c in value.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            '\t' | '\n' | '\r' => escaped.push(c),
+            c if c < ' ' || c == '\u{FFFE}' || c == '\u{FFFF}' => {}
+            c => escaped.push(c),
+        }
+    }
+
+    escaped
+}
+
+/// Renders a JUnit XML report: one test suite per project, one test case per job and one
+/// failure per finding, with the rule and the redacted line as message.
+pub fn render_junit(jobs: &[ScannedJob], findings: &[&Finding], timestamp: &str) -> String {
+    let mut projects: BTreeMap<&str, BTreeMap<u64, (ScannedJob, Vec<&Finding>)>> = BTreeMap::new();
+    for job in jobs {
+        projects
+            .entry(job.project_path)
+            .or_default()
+            .entry(job.job_id)
+            .or_insert_with(|| (*job, Vec::new()));
+    }
+    for finding in findings {
+        projects
+            .entry(&finding.project_path)
+            .or_default()
+            .entry(finding.job_id)
+            .or_insert_with(|| ((*finding).into(), Vec::new()))
+            .1
+            .push(finding);
+    }
+
+    let tests: usize = projects.values().map(|jobs| jobs.len()).sum();
+    let failures: usize = projects
+        .values()
+        .flat_map(|jobs| jobs.values())
+        .filter(|(_, findings)| !findings.is_empty())
+        .count();
+
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    // Writing to a String never fails.
+    let _ = writeln!(
+        xml,
+        "<testsuites name=\"gitlab-auditor\" tests=\"{}\" failures=\"{}\">",
+        tests, failures
+    );
+
+    for (project_path, jobs) in &projects {
+        let project_failures = jobs.values().filter(|(_, f)| !f.is_empty()).count();
+        let _ = writeln!(
+            xml,
+            "  <testsuite name=\"{}\" tests=\"{}\" failures=\"{}\" errors=\"0\" skipped=\"0\" timestamp=\"{}\">",
+            escape_xml(project_path),
+            jobs.len(),
+            project_failures,
+            escape_xml(timestamp)
+        );
+
+        for (job, findings) in jobs.values() {
+            let _ = writeln!(
+                xml,
+                "    <testcase name=\"{} #{}\" classname=\"{}\" time=\"0\">",
+                escape_xml(job.job_name),
+                job.job_id,
+                escape_xml(project_path)
+            );
+            xml.push_str("      <properties>\n");
+            for (name, value) in [
+                ("web_url", job.web_url),
+                ("ref", job.git_ref),
+                ("commit_sha", job.commit_sha),
+            ] {
+                let _ = writeln!(
+                    xml,
+                    "        <property name=\"{}\" value=\"{}\"/>",
+                    name,
+                    escape_xml(value)
+                );
+            }
+            xml.push_str("      </properties>\n");
+
+            for finding in findings {
+                let _ = writeln!(
+                    xml,
+                    "      <failure message=\"{}: {}\" type=\"{}\">{} (severity {}), line {}, column {}.</failure>",
+                    escape_xml(&finding.rule_id),
+                    escape_xml(&finding.redacted_line),
+                    finding.severity,
+                    escape_xml(&finding.description),
+                    finding.severity,
+                    finding.line,
+                    finding.column
+                );
+            }
+            xml.push_str("    </testcase>\n");
+        }
+        xml.push_str("  </testsuite>\n");
+    }
+    xml.push_str("</testsuites>\n");
+
+    xml
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::detect::test_finding;
+
+    #[test]
+    fn test_escape_xml() {
+        assert_eq!(
+            escape_xml("a < b && \"c\" > 'd'\u{1b}[0m"),
+            "a &lt; b &amp;&amp; &quot;c&quot; &gt; &apos;d&apos;[0m"
+        );
+    }
+
+    #[test]
+    fn test_one_suite_per_project_and_one_case_per_job() {
+        let mut leaky = test_finding(1, 10, 3);
+        leaky.redacted_line = "curl -H \"PRIVATE-TOKEN: [REDACTED:gitlab-pat]\" <url>".to_string();
+        let second = test_finding(1, 10, 4);
+        let clean = test_finding(2, 20, 1);
+        let jobs = [ScannedJob::from(&leaky), ScannedJob::from(&clean)];
+
+        let xml = render_junit(&jobs, &[&leaky, &second], "2025-03-14T09:26:53Z");
+
+        assert!(xml.contains("<testsuites name=\"gitlab-auditor\" tests=\"2\" failures=\"1\">"));
+        assert!(xml.contains("<testsuite name=\"group/project-1\" tests=\"1\" failures=\"1\""));
+        assert!(xml.contains("<testsuite name=\"group/project-2\" tests=\"1\" failures=\"0\""));
+        assert_eq!(xml.matches("<failure ").count(), 2);
+        assert!(xml.contains(
+            "message=\"gitlab-pat: curl -H &quot;PRIVATE-TOKEN: [REDACTED:gitlab-pat]&quot; &lt;url&gt;\""
+        ));
+        assert!(xml.contains(
+            "<property name=\"web_url\" value=\"https://gitlab.com/group/project-2/-/jobs/20\"/>"
+        ));
+        assert!(!xml.contains(&leaky.secret));
+    }
+}
@@ -0,0 +1,205 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+use clap::ValueEnum;
+
+use crate::detect::{Finding, Severity};
+use crate::report::Report;
+
+/// Escapes the characters Markdown would read as formatting or links, such as the `_` and `*`
+/// project and job names often contain.
+pub fn escape_markdown(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+
+    for c in text.chars() {
+        match c {
+            '\\' | '`' | '*' | '_' | '{' | '}' | '[' | ']' | '(' | ')' | '<' | '>' | '#' | '+'
+            | '-' | '.' | '!' | '|' | '~' => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            '\n' | '\r' => escaped.push(' '),
+            c => escaped.push(c),
+        }
+    }
+
+    escaped
+}
+
+/// Wraps text in a code span long enough for the backticks it contains.
+fn code_span(text: &str) -> String {
+    let longest = text.split(|c| c != '`').map(str::len).max().unwrap_or(0);
+    let fence = "`".repeat(longest + 1);
+    let text = text.replace(['\n', '\r'], " ");
+
+    format!("{} {} {}", fence, text, fence)
+}
+
+/// Keeps a link target from ending early or breaking out of the link.
+fn link_url(url: &str) -> String {
+    url.replace(' ', "%20")
+        .replace('(', "%28")
+        .replace(')', "%29")
+        .replace('<', "%3C")
+        .replace('>', "%3E")
+}
+
+fn severity_table(markdown: &mut String, findings: &[&Finding]) {
+    markdown.push_str("| Severity | Findings |\n|----------|---------:|\n");
+    for severity in Severity::value_variants().iter().rev() {
+        let count = findings.iter().filter(|f| f.severity == *severity).count();
+        let _ = writeln!(markdown, "| {} | {} |", severity, count);
+    }
+    markdown.push('\n');
+}
+
+/// One section per project, one list item per finding linking to its job.
+fn project_sections(markdown: &mut String, heading: &str, findings: &[&Finding]) {
+    let mut projects: BTreeMap<&str, Vec<&Finding>> = BTreeMap::new();
+    for finding in findings {
+        projects
+            .entry(&finding.project_path)
+            .or_default()
+            .push(finding);
+    }
+
+    for (project_path, mut findings) in projects {
+        findings.sort_by_key(|f| (std::cmp::Reverse(f.severity), f.job_id, f.line));
+
+        let _ = writeln!(markdown, "{} {}\n", heading, escape_markdown(project_path));
+        for finding in findings {
+            let _ = write!(
+                markdown,
+                "- **{}** {} in [{} #{}]({}), line {}: {}",
+                finding.severity,
+                escape_markdown(&finding.rule_id),
+                escape_markdown(&finding.job_name),
+                finding.job_id,
+                link_url(&finding.job_web_url),
+                finding.line,
+                code_span(&finding.redacted_line)
+            );
+            if let Some(verification) = finding.verification {
+                let _ = write!(markdown, " ({})", verification);
+            }
+            markdown.push('\n');
+        }
+        markdown.push('\n');
+    }
+}
+
+/// Renders a Markdown summary of a report to paste into merge requests and wiki pages: a
+/// severity table, the findings of each project and a coverage footer.
+pub fn render_markdown(report: &Report) -> String {
+    let findings: Vec<&Finding> = report.findings.iter().collect();
+    let mut markdown = String::from("# Gitlab audit report\n\n");
+
+    severity_table(&mut markdown, &findings);
+    if findings.is_empty() {
+        markdown.push_str("No secrets found.\n\n");
+    }
+    project_sections(&mut markdown, "##", &findings);
+
+    let _ = writeln!(
+        markdown,
+        "---\n\n_Scanned {} jobs of {} projects on {} at {}, {} findings suppressed by the baseline or allowlists._",
+        report.summary.jobs,
+        report.summary.projects,
+        escape_markdown(&report.instance_url),
+        escape_markdown(&report.generated_at),
+        report.summary.suppressed
+    );
+
+    markdown
+}
+
+/// Renders the new, still present, resolved and not rescanned findings of a comparison.
+pub fn render_diff_markdown(
+    new_findings: &[Finding],
+    still_present: &[Finding],
+    resolved: &[Finding],
+    not_rescanned: &[Finding],
+    generated_at: (&str, &str),
+) -> String {
+    let mut markdown = String::from("# Gitlab audit comparison\n\n");
+    let _ = writeln!(
+        markdown,
+        "| Findings | Count |\n|----------|------:|\n| New | {} |\n| Still present | {} |\n| Resolved | {} |\n| Not rescanned | {} |\n",
+        new_findings.len(),
+        still_present.len(),
+        resolved.len(),
+        not_rescanned.len()
+    );
+
+    for (title, findings) in [
+        ("New", new_findings),
+        ("Still present", still_present),
+        ("Resolved", resolved),
+        ("Not rescanned", not_rescanned),
+    ] {
+        if findings.is_empty() {
+            continue;
+        }
+        let _ = writeln!(markdown, "## {}\n", title);
+        project_sections(&mut markdown, "###", &findings.iter().collect::<Vec<_>>());
+    }
+
+    let _ = writeln!(
+        markdown,
+        "---\n\n_Compared the report of {} with the report of {}._",
+        escape_markdown(generated_at.0),
+        escape_markdown(generated_at.1)
+    );
+
+    markdown
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::detect::test_finding;
+    use crate::report::Summary;
+
+    #[test]
+    fn test_escape_markdown() {
+        assert_eq!(
+            escape_markdown("team_a/*api* [v2](x) #1 | <b>"),
+            "team\\_a/\\*api\\* \\[v2\\]\\(x\\) \\#1 \\| \\<b\\>"
+        );
+        assert_eq!(code_span("echo `id`"), "`` echo `id` ``");
+        assert_eq!(
+            link_url("https://gitlab.com/a (b)/-/jobs/1"),
+            "https://gitlab.com/a%20%28b%29/-/jobs/1"
+        );
+    }
+
+    #[test]
+    fn test_markdown_report() {
+        let mut finding = test_finding(1, 10, 3);
+        finding.project_path = "group/my_project".to_string();
+        finding.job_name = "deploy [prod]".to_string();
+        let report = Report {
+            generated_at: "2025-03-14T09:26:53Z".to_string(),
+            instance_url: "https://gitlab.com/".to_string(),
+            summary: Summary {
+                projects: 4,
+                jobs: 12,
+                findings: 1,
+                suppressed: 2,
+            },
+            findings: vec![finding.clone()],
+        };
+
+        let markdown = render_markdown(&report);
+
+        assert!(markdown.contains("| high | 1 |"));
+        assert!(markdown.contains("| critical | 0 |"));
+        assert!(markdown.contains("## group/my\\_project\n"));
+        assert!(markdown.contains(&format!(
+            "in [deploy \\[prod\\] #10]({}), line 3",
+            finding.job_web_url
+        )));
+        assert!(markdown.contains("Scanned 12 jobs of 4 projects"));
+        assert!(!markdown.contains(&finding.secret));
+    }
+}
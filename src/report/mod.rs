@@ -0,0 +1,107 @@
+pub mod diff;
+pub mod gitlab;
+pub mod junit;
+pub mod markdown;
+
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::crypto::read_artifact;
+use crate::detect::Finding;
+use crate::output::{ArtifactWriter, ManifestEntry, find_artifact};
+use crate::report::junit::{ScannedJob, render_junit};
+use crate::report::markdown::render_markdown;
+
+pub const REPORT_FILE: &str = "report.json";
+pub const JUNIT_REPORT_FILE: &str = "report.junit.xml";
+pub const MARKDOWN_REPORT_FILE: &str = "report.md";
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum ReportFormat {
+    #[default]
+    Json,
+    /// JUnit XML, for the Gitlab test report widget.
+    Junit,
+    /// A Markdown summary, for merge requests and wiki pages.
+    Markdown,
+}
+
+impl ReportFormat {
+    pub fn file_name(&self) -> &'static str {
+        match self {
+            ReportFormat::Json => REPORT_FILE,
+            ReportFormat::Junit => JUNIT_REPORT_FILE,
+            ReportFormat::Markdown => MARKDOWN_REPORT_FILE,
+        }
+    }
+}
+
+/// Findings of one run, written next to its manifest.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct Report {
+    pub generated_at: String,
+    pub instance_url: String,
+    pub summary: Summary,
+    pub findings: Vec<Finding>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct Summary {
+    pub projects: usize,
+    pub jobs: usize,
+    pub findings: usize,
+    // Findings matching the baseline or an allowlist, left out of the report.
+    #[serde(default)]
+    pub suppressed: usize,
+}
+
+impl Report {
+    pub fn write(&self, writer: &ArtifactWriter) -> Result<String, String> {
+        let content = serde_json::to_string_pretty(self)
+            .map_err(|e| format!("Failed to serialize report: {}", e))?;
+
+        writer.write(REPORT_FILE, content.as_bytes())
+    }
+
+    /// Writes the report in the other formats requested with `--report-format`, the JSON report
+    /// is always written by `write` since the other commands read it. The scanned jobs are the
+    /// test cases of the JUnit report.
+    pub fn write_formats(
+        &self,
+        writer: &ArtifactWriter,
+        formats: &[ReportFormat],
+        jobs: &[ManifestEntry],
+    ) -> Result<Vec<String>, String> {
+        let mut files = Vec::new();
+        for format in formats {
+            let content = match format {
+                ReportFormat::Json => continue,
+                ReportFormat::Junit => render_junit(
+                    &jobs.iter().map(ScannedJob::from).collect::<Vec<_>>(),
+                    &self.findings.iter().collect::<Vec<_>>(),
+                    &self.generated_at,
+                ),
+                ReportFormat::Markdown => render_markdown(self),
+            };
+            files.push(writer.write(format.file_name(), content.as_bytes())?);
+        }
+
+        Ok(files)
+    }
+
+    /// Reads a report file, or the report of a run directory.
+    pub fn read(path: &Path, identities: &[Box<dyn age::Identity>]) -> Result<Self, String> {
+        let path = if path.is_dir() {
+            find_artifact(path, REPORT_FILE)
+                .ok_or_else(|| format!("No {} found in {}", REPORT_FILE, path.display()))?
+        } else {
+            path.to_path_buf()
+        };
+        let content = read_artifact(&path, identities)?;
+
+        serde_json::from_slice(&content)
+            .map_err(|e| format!("Invalid report {}: {}", path.display(), e))
+    }
+}
@@ -0,0 +1,295 @@
+use chrono::{DateTime, NaiveDate, NaiveTime, Utc};
+use regex::Regex;
+use serde::Deserialize;
+
+use crate::scans::shared::Job;
+
+/// The statuses of finished jobs, the only ones with a complete log.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum JobStatus {
+    Success,
+    Failed,
+    Canceled,
+}
+
+impl JobStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            JobStatus::Success => "success",
+            JobStatus::Failed => "failed",
+            JobStatus::Canceled => "canceled",
+        }
+    }
+}
+
+const ALL_STATUSES: [JobStatus; 3] = [JobStatus::Success, JobStatus::Failed, JobStatus::Canceled];
+
+/// A shell-like pattern where `*` matches any characters and `?` a single one, as used by
+/// `--ref` and by Gitlab protected branches and tags.
+#[derive(Debug, Clone)]
+pub struct Glob {
+    pub pattern: String,
+    regex: Regex,
+}
+
+impl Glob {
+    pub fn new(pattern: &str) -> Result<Self, String> {
+        let mut regex = String::from("^");
+        for c in pattern.chars() {
+            match c {
+                '*' => regex.push_str(".*"),
+                '?' => regex.push('.'),
+                c => regex.push_str(&regex::escape(&c.to_string())),
+            }
+        }
+        regex.push('$');
+
+        Ok(Glob {
+            pattern: pattern.to_string(),
+            regex: Regex::new(&regex)
+                .map_err(|e| format!("Invalid pattern '{}': {}", pattern, e))?,
+        })
+    }
+
+    pub fn is_match(&self, value: &str) -> bool {
+        self.regex.is_match(value)
+    }
+
+    /// The pattern itself when it has no wildcard, so that Gitlab can filter on it.
+    pub fn literal(&self) -> Option<&str> {
+        (!self.pattern.contains(['*', '?'])).then_some(self.pattern.as_str())
+    }
+}
+
+/// Parses an RFC 3339 time or a `YYYY-MM-DD` date, taken at the start or at the end of the day.
+pub fn parse_time(value: &str, end_of_day: bool) -> Result<DateTime<Utc>, String> {
+    if let Ok(time) = DateTime::parse_from_rfc3339(value) {
+        return Ok(time.with_timezone(&Utc));
+    }
+
+    let date = NaiveDate::parse_from_str(value, "%Y-%m-%d").map_err(|_| {
+        format!(
+            "Invalid date '{}', expected YYYY-MM-DD or an RFC 3339 time",
+            value
+        )
+    })?;
+    let time = if end_of_day {
+        NaiveTime::from_hms_opt(23, 59, 59)
+    } else {
+        NaiveTime::from_hms_opt(0, 0, 0)
+    };
+
+    Ok(date.and_time(time.unwrap_or_default()).and_utc())
+}
+
+/// The protected branches and tags of a project, for `--protected-refs-only`.
+#[derive(Debug, Default)]
+pub struct ProtectedRefs {
+    pub branches: Vec<Glob>,
+    pub tags: Vec<Glob>,
+}
+
+impl ProtectedRefs {
+    pub fn is_protected(&self, job: &Job) -> bool {
+        let refs = if job.tag { &self.tags } else { &self.branches };
+        refs.iter().any(|glob| glob.is_match(&job.git_ref))
+    }
+}
+
+/// Which finished jobs a scan covers, every one of them by default.
+#[derive(Debug, Clone, Default)]
+pub struct JobFilter {
+    // Bounds on the job finished_at, both included.
+    pub since: Option<DateTime<Utc>>,
+    pub until: Option<DateTime<Utc>>,
+    // Empty for every finished status.
+    pub statuses: Vec<JobStatus>,
+    pub git_ref: Option<Glob>,
+    pub job_name: Option<Regex>,
+    pub protected_refs_only: bool,
+}
+
+impl JobFilter {
+    /// The `scope[]` query of the job listings, applied by Gitlab.
+    pub fn scope(&self) -> Vec<(&'static str, &'static str)> {
+        let statuses = if self.statuses.is_empty() {
+            &ALL_STATUSES[..]
+        } else {
+            &self.statuses[..]
+        };
+
+        statuses.iter().map(|s| ("scope[]", s.as_str())).collect()
+    }
+
+    /// The query of the pipeline listing when Gitlab can narrow the pipelines down, in which case
+    /// only the jobs of these pipelines are listed. A pipeline is updated when one of its jobs
+    /// finishes, so `updated_after` keeps every job finished since then.
+    pub fn pipeline_query(&self) -> Option<Vec<(&'static str, String)>> {
+        let mut query = Vec::new();
+        if let Some(since) = self.since {
+            query.push(("updated_after", since.to_rfc3339()));
+        }
+        if let Some(git_ref) = self.git_ref.as_ref().and_then(Glob::literal) {
+            query.push(("ref", git_ref.to_string()));
+        }
+
+        (!query.is_empty()).then_some(query)
+    }
+
+    /// The checks Gitlab cannot do, on each listed job.
+    pub fn matches(&self, job: &Job, protected: &ProtectedRefs) -> bool {
+        if self.since.is_some() || self.until.is_some() {
+            let finished_at = job
+                .finished_at
+                .as_deref()
+                .and_then(|t| DateTime::parse_from_rfc3339(t).ok());
+            let Some(finished_at) = finished_at else {
+                return false;
+            };
+            if self.since.is_some_and(|since| finished_at < since)
+                || self.until.is_some_and(|until| finished_at > until)
+            {
+                return false;
+            }
+        }
+
+        if !self.statuses.is_empty() && !self.statuses.iter().any(|s| s.as_str() == job.status) {
+            return false;
+        }
+
+        if let Some(git_ref) = &self.git_ref
+            && !git_ref.is_match(&job.git_ref)
+        {
+            return false;
+        }
+
+        if let Some(job_name) = &self.job_name
+            && !job_name.is_match(&job.name)
+        {
+            return false;
+        }
+
+        !self.protected_refs_only || protected.is_protected(job)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scans::shared::JobPipeline;
+
+    fn job(name: &str, git_ref: &str, status: &str, finished_at: Option<&str>) -> Job {
+        Job {
+            id: 1,
+            name: name.to_string(),
+            status: status.to_string(),
+            git_ref: git_ref.to_string(),
+            tag: false,
+            created_at: "2025-03-01T08:00:00Z".to_string(),
+            started_at: None,
+            finished_at: finished_at.map(str::to_string),
+            pipeline: JobPipeline {
+                id: 1,
+                sha: "abc123".to_string(),
+            },
+            web_url: "https://gitlab.com/group/project/-/jobs/1".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_glob() {
+        let glob = Glob::new("release/*").unwrap();
+        assert!(glob.is_match("release/1.2"));
+        assert!(!glob.is_match("main"));
+        assert!(!Glob::new("v1.?").unwrap().is_match("v1.10"));
+        assert_eq!(glob.literal(), None);
+        assert_eq!(Glob::new("main").unwrap().literal(), Some("main"));
+    }
+
+    #[test]
+    fn test_parse_time() {
+        assert_eq!(
+            parse_time("2025-03-01", false).unwrap().to_rfc3339(),
+            "2025-03-01T00:00:00+00:00"
+        );
+        assert_eq!(
+            parse_time("2025-03-01", true).unwrap().to_rfc3339(),
+            "2025-03-01T23:59:59+00:00"
+        );
+        assert_eq!(
+            parse_time("2025-03-01T10:00:00+02:00", false)
+                .unwrap()
+                .to_rfc3339(),
+            "2025-03-01T08:00:00+00:00"
+        );
+        assert!(parse_time("01/03/2025", false).is_err());
+    }
+
+    #[test]
+    fn test_default_filter_keeps_every_finished_job() {
+        let filter = JobFilter::default();
+
+        assert!(filter.matches(
+            &job("build", "main", "success", None),
+            &ProtectedRefs::default()
+        ));
+        assert_eq!(filter.scope().len(), 3);
+        assert!(filter.pipeline_query().is_none());
+    }
+
+    #[test]
+    fn test_filter_on_finished_at_name_and_ref() {
+        let filter = JobFilter {
+            since: Some(parse_time("2025-03-01", false).unwrap()),
+            until: Some(parse_time("2025-03-31", true).unwrap()),
+            statuses: vec![JobStatus::Failed],
+            git_ref: Some(Glob::new("release/*").unwrap()),
+            job_name: Some(Regex::new("^deploy").unwrap()),
+            protected_refs_only: false,
+        };
+        let none = ProtectedRefs::default();
+
+        let finished = Some("2025-03-31T12:00:00Z");
+        assert!(filter.matches(&job("deploy-prod", "release/2", "failed", finished), &none));
+        assert!(!filter.matches(&job("deploy-prod", "release/2", "failed", None), &none));
+        assert!(!filter.matches(
+            &job(
+                "deploy-prod",
+                "release/2",
+                "failed",
+                Some("2025-04-01T00:00:00Z")
+            ),
+            &none
+        ));
+        assert!(!filter.matches(&job("deploy-prod", "release/2", "success", finished), &none));
+        assert!(!filter.matches(&job("deploy-prod", "main", "failed", finished), &none));
+        assert!(!filter.matches(&job("build", "release/2", "failed", finished), &none));
+
+        assert_eq!(filter.scope(), vec![("scope[]", "failed")]);
+        assert_eq!(
+            filter.pipeline_query().unwrap(),
+            vec![("updated_after", "2025-03-01T00:00:00+00:00".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_protected_refs_only() {
+        let filter = JobFilter {
+            protected_refs_only: true,
+            ..Default::default()
+        };
+        let protected = ProtectedRefs {
+            branches: vec![Glob::new("main").unwrap(), Glob::new("stable-*").unwrap()],
+            tags: vec![Glob::new("v*").unwrap()],
+        };
+
+        assert!(filter.matches(&job("build", "stable-2", "success", None), &protected));
+        assert!(!filter.matches(&job("build", "feature", "success", None), &protected));
+
+        let mut tag = job("build", "v1.0", "success", None);
+        assert!(!filter.matches(&tag, &protected));
+        tag.tag = true;
+        assert!(filter.matches(&tag, &protected));
+    }
+}
@@ -1,2 +1,6 @@
+pub mod filter;
 pub mod full;
+pub mod namespace;
 pub mod shared;
+pub mod trace;
+pub mod watch;
@@ -0,0 +1,114 @@
+use std::collections::BTreeMap;
+
+use super::shared::{Group, Project};
+
+/// The visible groups of an instance, by ID.
+#[derive(Debug, Default)]
+pub struct NamespaceTree {
+    groups: BTreeMap<u64, Group>,
+}
+
+impl NamespaceTree {
+    pub fn new(groups: Vec<Group>) -> Self {
+        NamespaceTree {
+            groups: groups.into_iter().map(|g| (g.id, g)).collect(),
+        }
+    }
+
+    /// Groups whose parent is not visible to the token. Listing their projects with
+    /// subgroups included covers the whole tree, each subgroup does not need its own call.
+    pub fn roots(&self) -> Vec<&Group> {
+        self.groups
+            .values()
+            .filter(|group| {
+                group
+                    .parent_id
+                    .is_none_or(|parent_id| !self.groups.contains_key(&parent_id))
+            })
+            .collect()
+    }
+
+    pub fn groups(&self) -> Vec<&Group> {
+        self.groups.values().collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.groups.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.groups.is_empty()
+    }
+}
+
+/// Merges projects found by several discovery methods, keeping each project ID once.
+pub fn merge_projects(project_sets: Vec<Vec<Project>>) -> Vec<Project> {
+    let mut projects = BTreeMap::new();
+
+    for project in project_sets.into_iter().flatten() {
+        projects.entry(project.id).or_insert(project);
+    }
+
+    projects.into_values().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn group(id: u64, full_path: &str, parent_id: Option<u64>) -> Group {
+        Group {
+            id,
+            name: full_path.rsplit('/').next().unwrap().to_string(),
+            full_path: full_path.to_string(),
+            parent_id,
+        }
+    }
+
+    fn project(id: u64, path_with_namespace: &str) -> Project {
+        Project {
+            id,
+            name: path_with_namespace.rsplit('/').next().unwrap().to_string(),
+            path_with_namespace: path_with_namespace.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_roots_skip_subgroups() {
+        let tree = NamespaceTree::new(vec![
+            group(1, "a", None),
+            group(2, "a/b", Some(1)),
+            group(3, "a/b/c", Some(2)),
+            group(4, "d", None),
+        ]);
+
+        let roots: Vec<_> = tree.roots().iter().map(|g| g.id).collect();
+        assert_eq!(roots, vec![1, 4]);
+    }
+
+    #[test]
+    fn test_subgroup_with_hidden_parent_is_root() {
+        // The token is a member of a/b/c only, a and a/b are not listed.
+        let tree = NamespaceTree::new(vec![group(3, "a/b/c", Some(2))]);
+
+        let roots: Vec<_> = tree.roots().iter().map(|g| g.id).collect();
+        assert_eq!(roots, vec![3]);
+    }
+
+    #[test]
+    fn test_merge_projects_deduplicates_by_id() {
+        let from_a = vec![project(2, "a/b/c/api"), project(1, "a/web")];
+        let from_a_b = vec![project(2, "a/b/c/api")];
+        let outside_groups = vec![project(3, "jdoe/sandbox"), project(2, "a/b/c/api")];
+
+        let projects = merge_projects(vec![from_a, from_a_b, outside_groups]);
+
+        assert_eq!(
+            projects
+                .iter()
+                .map(|p| p.path_with_namespace.as_str())
+                .collect::<Vec<_>>(),
+            vec!["a/web", "a/b/c/api", "jdoe/sandbox"]
+        );
+    }
+}
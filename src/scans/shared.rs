@@ -1,18 +1,126 @@
 pub const PRIVATE_TOKEN_HEADER: &str = "PRIVATE-TOKEN";
+pub const JOB_TOKEN_HEADER: &str = "JOB-TOKEN";
 pub const MIN_ACCESS_LEVEL_GUEST: &str = "10";
+pub const GITLAB_API_SEGMENTS: [&str; 2] = ["api", "v4"];
 
 use serde::{Deserialize, Serialize};
+use url::Url;
+
+/// Appends the API path to an instance URL, keeping any relative URL root
+/// such as `https://corp.example.com/gitlab`.
+pub fn api_url(instance_url: &Url) -> Option<Url> {
+    let mut url = instance_root(instance_url);
+    url.path_segments_mut()
+        .ok()?
+        .pop_if_empty()
+        .extend(GITLAB_API_SEGMENTS);
+    Some(url)
+}
+
+/// Strips a trailing slash and the API path, if present, from an instance or API URL.
+fn instance_root(url: &Url) -> Url {
+    let mut url = url.clone();
+    url.set_query(None);
+    url.set_fragment(None);
+
+    let mut segments: Vec<String> = url
+        .path_segments()
+        .map(|segments| {
+            segments
+                .filter(|s| !s.is_empty())
+                .map(str::to_string)
+                .collect()
+        })
+        .unwrap_or_default();
+
+    if segments.ends_with(&GITLAB_API_SEGMENTS.map(str::to_string)) {
+        segments.truncate(segments.len() - GITLAB_API_SEGMENTS.len());
+    }
+
+    url.set_path(&segments.join("/"));
+    url
+}
+
+/// API roots to probe, from the full path given by the user up to the host root,
+/// e.g. `/gitlab/explore/api/v4`, `/gitlab/api/v4` then `/api/v4`.
+pub fn api_root_candidates(url: &Url) -> Vec<Url> {
+    let mut root = instance_root(url);
+    let mut candidates = Vec::new();
+
+    loop {
+        if let Some(candidate) = api_url(&root) {
+            candidates.push(candidate);
+        }
+
+        let path = root.path().trim_end_matches('/').to_string();
+        match path.rfind('/') {
+            Some(index) if !path.is_empty() => root.set_path(&path[..index]),
+            _ => break,
+        }
+    }
+
+    candidates
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Version {
+    pub version: String,
+    // Full response format: https://docs.gitlab.com/api/version/
+}
+
+/// Finds the real API root of the instance by probing `<candidate>/version`.
+pub async fn detect_api_root(
+    client: &reqwest::Client,
+    token: &str,
+    url: &Url,
+) -> Result<(Url, Version), String> {
+    let candidates = api_root_candidates(url);
+
+    for candidate in &candidates {
+        let response = match client
+            .get(format!("{}/version", candidate))
+            .header(PRIVATE_TOKEN_HEADER, token)
+            .send()
+            .await
+        {
+            Ok(response) => response,
+            Err(e) => return Err(format!("Failed to reach {}: {}", candidate, e)),
+        };
+
+        match response.status() {
+            status if status.is_success() => {
+                if let Ok(version) = response.json::<Version>().await {
+                    return Ok((candidate.clone(), version));
+                }
+            }
+            reqwest::StatusCode::UNAUTHORIZED => {
+                return Err(format!("The Gitlab token was rejected by {}", candidate));
+            }
+            _ => {}
+        }
+    }
+
+    Err(format!(
+        "No Gitlab API found, tried: {}",
+        candidates
+            .iter()
+            .map(|c| c.as_str())
+            .collect::<Vec<_>>()
+            .join(", ")
+    ))
+}
 
 #[derive(Debug, Deserialize, Serialize)]
 pub struct Project {
     pub id: u64,
     pub name: String,
+    pub path_with_namespace: String,
     // Full response format: https://docs.gitlab.com/api/groups/#list-projects
 }
 
 impl std::fmt::Display for Project {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "Project {} (ID: {})", self.name, self.id)
+        write!(f, "Project {} (ID: {})", self.path_with_namespace, self.id)
     }
 }
 
@@ -20,12 +128,29 @@ impl std::fmt::Display for Project {
 pub struct Group {
     pub id: u64,
     pub name: String,
+    pub full_path: String,
+    pub parent_id: Option<u64>,
     // Full response format: https://docs.gitlab.com/ee/api/groups.html#list-groups
 }
 
 impl std::fmt::Display for Group {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "Group {} (ID: {})", self.name, self.id)
+        write!(f, "Group {} (ID: {})", self.full_path, self.id)
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct User {
+    pub id: u64,
+    pub username: String,
+    // Only returned to administrators and for the current user.
+    pub is_admin: Option<bool>,
+    // Full response format: https://docs.gitlab.com/api/users/#list-users
+}
+
+impl std::fmt::Display for User {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "User {} (ID: {})", self.username, self.id)
     }
 }
 
@@ -33,11 +158,19 @@ impl std::fmt::Display for Group {
 pub struct Pipeline {
     pub id: u64,
     pub project_id: u64,
+    #[serde(rename = "ref")]
     pub branch_ref: String,
     pub status: String,
     // Full response format: https://docs.gitlab.com/api/pipelines/#list-project-pipelines
 }
 
+/// A protected branch or tag, whose name may contain `*` wildcards.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ProtectedRef {
+    pub name: String,
+    // Full response format: https://docs.gitlab.com/api/protected_branches/#list-protected-branches
+}
+
 impl std::fmt::Display for Pipeline {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(
@@ -52,11 +185,26 @@ impl std::fmt::Display for Pipeline {
 pub struct Job {
     pub id: u64,
     pub name: String,
-
+    pub status: String,
+    #[serde(rename = "ref")]
+    pub git_ref: String,
+    // Whether the ref is a tag rather than a branch.
+    #[serde(default)]
+    pub tag: bool,
+    pub created_at: String,
+    pub started_at: Option<String>,
+    pub finished_at: Option<String>,
+    pub pipeline: JobPipeline,
     pub web_url: String,
     // Full response format: https://docs.gitlab.com/api/jobs/#list-project-jobs
 }
 
+#[derive(Debug, Deserialize, Serialize)]
+pub struct JobPipeline {
+    pub id: u64,
+    pub sha: String,
+}
+
 impl std::fmt::Display for Job {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "Job {} (ID: {})", self.name, self.id)
@@ -76,3 +224,49 @@ impl std::fmt::Display for Artifact {
         write!(f, "Artifact {} (Size: {})", self.filename, self.size)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn url(value: &str) -> Url {
+        value.parse().unwrap()
+    }
+
+    #[test]
+    fn test_api_url_keeps_relative_root() {
+        assert_eq!(
+            api_url(&url("https://corp.example.com/gitlab"))
+                .unwrap()
+                .as_str(),
+            "https://corp.example.com/gitlab/api/v4"
+        );
+    }
+
+    #[test]
+    fn test_api_url_is_idempotent() {
+        let api = api_url(&url("https://gitlab.com")).unwrap();
+        assert_eq!(api_url(&api).unwrap(), api);
+    }
+
+    #[test]
+    fn test_api_root_candidates() {
+        let candidates = api_root_candidates(&url("https://corp.example.com/gitlab/api/v4"));
+        assert_eq!(
+            candidates.iter().map(Url::as_str).collect::<Vec<_>>(),
+            vec![
+                "https://corp.example.com/gitlab/api/v4",
+                "https://corp.example.com/api/v4"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_api_root_candidates_at_host_root() {
+        let candidates = api_root_candidates(&url("https://gitlab.com/api/v4"));
+        assert_eq!(
+            candidates.iter().map(Url::as_str).collect::<Vec<_>>(),
+            vec!["https://gitlab.com/api/v4"]
+        );
+    }
+}
@@ -0,0 +1,257 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::scans::filter::JobFilter;
+
+pub const WATCH_STATE_FILE: &str = "watch-state.json";
+
+/// How far before the newest finish it saw a poll starts the next one, for jobs whose finish
+/// was recorded late. The jobs scanned in that overlap are not scanned again.
+const OVERLAP_MINUTES: i64 = 5;
+
+/// Where the watch of each project stands, so that a restarted watch resumes where it stopped.
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct WatchState {
+    pub last_poll: Option<String>,
+    pub projects: BTreeMap<u64, ProjectCursor>,
+}
+
+/// The jobs of a project still to scan: the ones finished since the cursor and not scanned yet.
+#[derive(Debug, Default, Clone, Deserialize, Serialize)]
+pub struct ProjectCursor {
+    // Every job finished before this time was scanned.
+    pub finished_since: Option<String>,
+    // The jobs finished since then already scanned, with their finish time.
+    #[serde(default)]
+    pub scanned: BTreeMap<u64, String>,
+}
+
+/// A job listed by a poll, and whether its trace was stored.
+#[derive(Debug, Clone)]
+pub struct PolledJob {
+    pub id: u64,
+    pub finished_at: Option<DateTime<Utc>>,
+    pub stored: bool,
+}
+
+fn parse(time: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(time)
+        .ok()
+        .map(|t| t.with_timezone(&Utc))
+}
+
+impl ProjectCursor {
+    pub fn since(&self) -> Option<DateTime<Utc>> {
+        self.finished_since.as_deref().and_then(parse)
+    }
+
+    /// The filter of the next poll, the jobs finished before the cursor are left out. A job is
+    /// listed when it finishes, however long ago it was created.
+    pub fn narrow(&self, filter: &JobFilter) -> JobFilter {
+        let mut filter = filter.clone();
+        filter.since = filter.since.max(self.since());
+        filter
+    }
+
+    pub fn is_scanned(&self, job_id: u64) -> bool {
+        self.scanned.contains_key(&job_id)
+    }
+
+    /// Moves the cursor past the jobs of a poll. It stops at the oldest job whose trace could not
+    /// be stored, which is fetched again at the next poll while the stored ones are skipped.
+    pub fn record(&mut self, jobs: &[PolledJob]) {
+        for job in jobs.iter().filter(|j| j.stored) {
+            if let Some(finished_at) = job.finished_at {
+                self.scanned.insert(job.id, finished_at.to_rfc3339());
+            }
+        }
+
+        // The jobs skipped by the poll as already scanned count as seen too.
+        let newest = jobs
+            .iter()
+            .filter_map(|j| j.finished_at)
+            .chain(self.scanned.values().filter_map(|t| parse(t)))
+            .max();
+        let Some(newest) = newest else {
+            return;
+        };
+        let mut since = newest - Duration::minutes(OVERLAP_MINUTES);
+        if let Some(failed) = jobs
+            .iter()
+            .filter(|j| !j.stored)
+            .filter_map(|j| j.finished_at)
+            .min()
+        {
+            since = since.min(failed);
+        }
+        since = since.max(self.since().unwrap_or(since));
+
+        self.finished_since = Some(since.to_rfc3339());
+        self.scanned
+            .retain(|_, finished_at| parse(finished_at).is_none_or(|t| t >= since));
+    }
+}
+
+impl WatchState {
+    /// Loads the state, a missing file is the state of a first poll.
+    pub fn load(path: &Path) -> Result<Self, String> {
+        if !path.exists() {
+            return Ok(WatchState::default());
+        }
+
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read watch state {}: {}", path.display(), e))?;
+
+        serde_json::from_str(&content)
+            .map_err(|e| format!("Invalid watch state {}: {}", path.display(), e))
+    }
+
+    /// Writes the state through a temporary file, an interrupted write never loses it.
+    pub fn write(&self, path: &Path) -> Result<(), String> {
+        let content = serde_json::to_string_pretty(self)
+            .map_err(|e| format!("Failed to serialize watch state: {}", e))?;
+        let temporary = path.with_extension("json.tmp");
+
+        std::fs::write(&temporary, content)
+            .and_then(|()| std::fs::rename(&temporary, path))
+            .map_err(|e| format!("Failed to write watch state {}: {}", path.display(), e))
+    }
+
+    pub fn cursor(&self, project_id: u64) -> Option<&ProjectCursor> {
+        self.projects.get(&project_id)
+    }
+
+    pub fn record(&mut self, polled: &BTreeMap<u64, Vec<PolledJob>>) {
+        for (project_id, jobs) in polled {
+            self.projects.entry(*project_id).or_default().record(jobs);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scans::filter::{ProtectedRefs, parse_time};
+    use crate::scans::shared::{Job, JobPipeline};
+
+    fn polled(id: u64, finished_at: &str, stored: bool) -> PolledJob {
+        PolledJob {
+            id,
+            finished_at: parse_time(finished_at, false).ok(),
+            stored,
+        }
+    }
+
+    fn job(id: u64, created_at: &str, finished_at: &str) -> Job {
+        Job {
+            id,
+            name: "build".to_string(),
+            status: "success".to_string(),
+            git_ref: "main".to_string(),
+            tag: false,
+            created_at: created_at.to_string(),
+            started_at: None,
+            finished_at: Some(finished_at.to_string()),
+            pipeline: JobPipeline {
+                id: 1,
+                sha: "abc123".to_string(),
+            },
+            web_url: format!("https://gitlab.com/group/project/-/jobs/{}", id),
+        }
+    }
+
+    #[test]
+    fn test_failed_traces_hold_the_cursor() {
+        let mut cursor = ProjectCursor::default();
+        cursor.record(&[
+            polled(14, "2025-03-01T10:50:00Z", true),
+            polled(13, "2025-03-01T10:20:00Z", false),
+            polled(12, "2025-03-01T10:10:00Z", true),
+        ]);
+
+        assert_eq!(
+            cursor.finished_since.as_deref(),
+            Some("2025-03-01T10:20:00+00:00")
+        );
+        assert!(cursor.is_scanned(14));
+        assert!(!cursor.is_scanned(13));
+        // Finished before the cursor, it is not listed again.
+        assert!(!cursor.is_scanned(12));
+
+        cursor.record(&[polled(13, "2025-03-01T10:20:00Z", true)]);
+        assert_eq!(
+            cursor.finished_since.as_deref(),
+            Some("2025-03-01T10:45:00+00:00")
+        );
+        assert!(cursor.is_scanned(14));
+        assert!(!cursor.is_scanned(13));
+
+        // A poll without jobs keeps the cursor.
+        cursor.record(&[]);
+        assert_eq!(
+            cursor.finished_since.as_deref(),
+            Some("2025-03-01T10:45:00+00:00")
+        );
+    }
+
+    #[test]
+    fn test_older_job_finishing_late_is_polled() {
+        let mut cursor = ProjectCursor::default();
+        // Job 21 started after job 20 but finished first.
+        cursor.record(&[polled(21, "2025-03-01T10:35:00Z", true)]);
+
+        let filter = cursor.narrow(&JobFilter::default());
+        let none = ProtectedRefs::default();
+        let late = job(20, "2025-03-01T10:00:00Z", "2025-03-01T11:00:00Z");
+        let scanned = job(21, "2025-03-01T10:30:00Z", "2025-03-01T10:35:00Z");
+
+        assert!(filter.matches(&late, &none) && !cursor.is_scanned(late.id));
+        assert!(cursor.is_scanned(scanned.id));
+        assert!(!filter.matches(
+            &job(19, "2025-03-01T09:00:00Z", "2025-03-01T09:30:00Z"),
+            &none
+        ));
+    }
+
+    #[test]
+    fn test_watch_state_round_trip() {
+        let path = std::env::temp_dir().join(format!(
+            "gitlab_auditor_watch_state_{}.json",
+            std::process::id()
+        ));
+        let mut state = WatchState::load(&path).unwrap();
+        assert!(state.cursor(1).is_none());
+
+        state.record(
+            &[
+                (1, vec![polled(20, "2025-03-01T12:00:00Z", true)]),
+                (2, vec![polled(5, "2025-03-01T08:00:00Z", false)]),
+            ]
+            .into_iter()
+            .collect(),
+        );
+        state.record(
+            &[(1, vec![polled(18, "2025-03-01T09:00:00Z", true)])]
+                .into_iter()
+                .collect(),
+        );
+        state.write(&path).unwrap();
+
+        let loaded = WatchState::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let first = loaded.cursor(1).unwrap();
+        assert_eq!(
+            first.finished_since.as_deref(),
+            Some("2025-03-01T11:55:00+00:00")
+        );
+        assert!(first.is_scanned(20));
+        assert_eq!(
+            loaded.cursor(2).unwrap().finished_since.as_deref(),
+            Some("2025-03-01T07:55:00+00:00")
+        );
+    }
+}
@@ -0,0 +1,489 @@
+use std::path::Path;
+
+use rusqlite::types::ValueRef;
+use rusqlite::{Connection, OpenFlags, ToSql, params};
+
+use crate::detect::Severity;
+use crate::output::ManifestEntry;
+use crate::report::Report;
+use crate::scans::shared::{Group, Project};
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS runs (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    started_at TEXT NOT NULL,
+    finished_at TEXT NOT NULL,
+    instance_url TEXT NOT NULL,
+    run_dir TEXT NOT NULL,
+    projects INTEGER NOT NULL,
+    jobs INTEGER NOT NULL,
+    findings INTEGER NOT NULL,
+    suppressed INTEGER NOT NULL
+);
+CREATE TABLE IF NOT EXISTS groups (
+    id INTEGER PRIMARY KEY,
+    name TEXT NOT NULL,
+    full_path TEXT NOT NULL,
+    parent_id INTEGER,
+    last_run_id INTEGER NOT NULL
+);
+CREATE TABLE IF NOT EXISTS projects (
+    id INTEGER PRIMARY KEY,
+    name TEXT NOT NULL,
+    path_with_namespace TEXT NOT NULL,
+    last_run_id INTEGER NOT NULL
+);
+CREATE TABLE IF NOT EXISTS pipelines (
+    id INTEGER PRIMARY KEY,
+    project_id INTEGER NOT NULL,
+    ref TEXT NOT NULL,
+    sha TEXT NOT NULL
+);
+CREATE TABLE IF NOT EXISTS jobs (
+    id INTEGER PRIMARY KEY,
+    project_id INTEGER NOT NULL,
+    pipeline_id INTEGER NOT NULL,
+    name TEXT NOT NULL,
+    status TEXT NOT NULL,
+    ref TEXT NOT NULL,
+    created_at TEXT NOT NULL,
+    started_at TEXT,
+    finished_at TEXT,
+    web_url TEXT NOT NULL,
+    file TEXT NOT NULL,
+    last_run_id INTEGER NOT NULL
+);
+CREATE TABLE IF NOT EXISTS findings (
+    run_id INTEGER NOT NULL REFERENCES runs (id),
+    fingerprint TEXT NOT NULL,
+    id TEXT NOT NULL,
+    rule_id TEXT NOT NULL,
+    description TEXT NOT NULL,
+    severity TEXT NOT NULL,
+    project_id INTEGER NOT NULL,
+    project_path TEXT NOT NULL,
+    pipeline_id INTEGER NOT NULL,
+    job_id INTEGER NOT NULL,
+    job_name TEXT NOT NULL,
+    job_web_url TEXT NOT NULL,
+    ref TEXT NOT NULL,
+    commit_sha TEXT NOT NULL,
+    file TEXT NOT NULL,
+    line INTEGER NOT NULL,
+    column INTEGER NOT NULL,
+    redacted_line TEXT NOT NULL,
+    verification TEXT,
+    revoked INTEGER NOT NULL,
+    PRIMARY KEY (run_id, fingerprint)
+);
+CREATE INDEX IF NOT EXISTS findings_rule ON findings (rule_id);
+CREATE INDEX IF NOT EXISTS findings_project ON findings (project_path);
+";
+
+/// The inventory, jobs and findings of every run, kept in an embedded SQLite database.
+pub struct Store {
+    conn: Connection,
+}
+
+/// What a run saw, recorded in one transaction.
+pub struct RunRecord<'a> {
+    pub started_at: &'a str,
+    pub run_dir: &'a Path,
+    pub report: &'a Report,
+    pub groups: &'a [&'a Group],
+    pub projects: &'a [Project],
+    pub jobs: &'a [ManifestEntry],
+}
+
+/// Canned filters of the `query` command, every given filter must match.
+#[derive(Debug, Clone, Default)]
+pub struct FindingFilter {
+    // A project path, or a namespace holding the projects.
+    pub project: Option<String>,
+    pub rule: Option<String>,
+    pub min_severity: Option<Severity>,
+    // Dates compared with the creation of the leaking job, e.g. 2025-01-31.
+    pub since: Option<String>,
+    pub until: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct QueryResult {
+    pub columns: Vec<String>,
+    pub rows: Vec<Vec<String>>,
+}
+
+impl QueryResult {
+    /// Tab-separated values with a header line, ready for `column -t` or a spreadsheet.
+    pub fn to_tsv(&self) -> String {
+        let mut lines = vec![self.columns.join("\t")];
+        lines.extend(self.rows.iter().map(|row| row.join("\t")));
+        lines.join("\n")
+    }
+}
+
+fn sql_error(e: rusqlite::Error) -> String {
+    format!("Database error: {}", e)
+}
+
+const SEVERITIES: [Severity; 4] = [
+    Severity::Low,
+    Severity::Medium,
+    Severity::High,
+    Severity::Critical,
+];
+
+impl Store {
+    /// Opens the database, creating it and its tables when needed.
+    pub fn open(path: &Path) -> Result<Self, String> {
+        let conn = Connection::open(path)
+            .map_err(|e| format!("Failed to open database {}: {}", path.display(), e))?;
+        conn.execute_batch(SCHEMA).map_err(sql_error)?;
+
+        Ok(Store { conn })
+    }
+
+    /// Opens an existing database, raw SQL from the command line cannot modify it.
+    pub fn open_read_only(path: &Path) -> Result<Self, String> {
+        let conn = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)
+            .map_err(|e| format!("Failed to open database {}: {}", path.display(), e))?;
+
+        Ok(Store { conn })
+    }
+
+    pub fn record_run(&mut self, run: &RunRecord) -> Result<i64, String> {
+        let tx = self.conn.transaction().map_err(sql_error)?;
+        let report = run.report;
+
+        tx.execute(
+            "INSERT INTO runs (started_at, finished_at, instance_url, run_dir, projects, jobs, findings, suppressed)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            params![
+                run.started_at,
+                report.generated_at,
+                report.instance_url,
+                run.run_dir.display().to_string(),
+                report.summary.projects,
+                report.summary.jobs,
+                report.summary.findings,
+                report.summary.suppressed,
+            ],
+        )
+        .map_err(sql_error)?;
+        let run_id = tx.last_insert_rowid();
+
+        for group in run.groups {
+            tx.execute(
+                "INSERT OR REPLACE INTO groups (id, name, full_path, parent_id, last_run_id)
+                 VALUES (?1, ?2, ?3, ?4, ?5)",
+                params![
+                    group.id,
+                    group.name,
+                    group.full_path,
+                    group.parent_id,
+                    run_id
+                ],
+            )
+            .map_err(sql_error)?;
+        }
+
+        for project in run.projects {
+            tx.execute(
+                "INSERT OR REPLACE INTO projects (id, name, path_with_namespace, last_run_id)
+                 VALUES (?1, ?2, ?3, ?4)",
+                params![
+                    project.id,
+                    project.name,
+                    project.path_with_namespace,
+                    run_id
+                ],
+            )
+            .map_err(sql_error)?;
+        }
+
+        for job in run.jobs {
+            tx.execute(
+                "INSERT OR REPLACE INTO pipelines (id, project_id, ref, sha) VALUES (?1, ?2, ?3, ?4)",
+                params![job.pipeline_id, job.project_id, job.git_ref, job.commit_sha],
+            )
+            .map_err(sql_error)?;
+            tx.execute(
+                "INSERT OR REPLACE INTO jobs (id, project_id, pipeline_id, name, status, ref, created_at, started_at, finished_at, web_url, file, last_run_id)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)",
+                params![
+                    job.job_id,
+                    job.project_id,
+                    job.pipeline_id,
+                    job.job_name,
+                    job.job_status,
+                    job.git_ref,
+                    job.created_at,
+                    job.started_at,
+                    job.finished_at,
+                    job.job_web_url,
+                    job.file,
+                    run_id,
+                ],
+            )
+            .map_err(sql_error)?;
+        }
+
+        for finding in &report.findings {
+            tx.execute(
+                "INSERT OR REPLACE INTO findings (run_id, fingerprint, id, rule_id, description, severity, project_id, project_path, pipeline_id, job_id, job_name, job_web_url, ref, commit_sha, file, line, column, redacted_line, verification, revoked)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19, ?20)",
+                params![
+                    run_id,
+                    finding.fingerprint,
+                    finding.id,
+                    finding.rule_id,
+                    finding.description,
+                    finding.severity.to_string(),
+                    finding.project_id,
+                    finding.project_path,
+                    finding.pipeline_id,
+                    finding.job_id,
+                    finding.job_name,
+                    finding.job_web_url,
+                    finding.git_ref,
+                    finding.commit_sha,
+                    finding.file,
+                    finding.line,
+                    finding.column,
+                    finding.redacted_line,
+                    finding.verification.map(|v| v.to_string()),
+                    finding.revoked,
+                ],
+            )
+            .map_err(sql_error)?;
+        }
+
+        tx.commit().map_err(sql_error)?;
+        Ok(run_id)
+    }
+
+    /// Findings matching the filters, each fingerprint once with the run that last saw it.
+    pub fn query_findings(&self, filter: &FindingFilter) -> Result<QueryResult, String> {
+        let mut conditions: Vec<String> = Vec::new();
+        let mut values: Vec<Box<dyn ToSql>> = Vec::new();
+
+        if let Some(project) = &filter.project {
+            // A prefix comparison rather than LIKE, where the `_` of many paths is a wildcard.
+            values.push(Box::new(project.trim_matches('/').to_string()));
+            conditions.push(format!(
+                "(f.project_path = ?{0} OR substr(f.project_path, 1, length(?{0}) + 1) = ?{0} || '/')",
+                values.len()
+            ));
+        }
+        if let Some(rule) = &filter.rule {
+            values.push(Box::new(rule.clone()));
+            conditions.push(format!("f.rule_id = ?{}", values.len()));
+        }
+        if let Some(min_severity) = filter.min_severity {
+            let severities: Vec<String> = SEVERITIES
+                .iter()
+                .filter(|s| **s >= min_severity)
+                .map(|s| format!("'{}'", s))
+                .collect();
+            conditions.push(format!("f.severity IN ({})", severities.join(", ")));
+        }
+        if let Some(since) = &filter.since {
+            values.push(Box::new(since.clone()));
+            conditions.push(format!("j.created_at >= ?{}", values.len()));
+        }
+        if let Some(until) = &filter.until {
+            // Compared on the length of the bound, a bare date includes the whole day.
+            values.push(Box::new(until.clone()));
+            conditions.push(format!(
+                "substr(j.created_at, 1, length(?{0})) <= ?{0}",
+                values.len()
+            ));
+        }
+
+        let where_clause = if conditions.is_empty() {
+            String::new()
+        } else {
+            format!("WHERE {}", conditions.join(" AND "))
+        };
+        let sql = format!(
+            "SELECT f.severity, f.rule_id, f.project_path, f.job_id, j.created_at AS job_created_at,
+                    f.job_web_url, f.verification, f.fingerprint, MAX(r.started_at) AS last_seen
+             FROM findings f
+             JOIN runs r ON r.id = f.run_id
+             LEFT JOIN jobs j ON j.id = f.job_id
+             {}
+             GROUP BY f.fingerprint
+             ORDER BY job_created_at DESC, f.project_path, f.job_id",
+            where_clause
+        );
+
+        let params: Vec<&dyn ToSql> = values.iter().map(|v| v.as_ref()).collect();
+        self.query(&sql, &params)
+    }
+
+    pub fn query_sql(&self, sql: &str) -> Result<QueryResult, String> {
+        self.query(sql, &[])
+    }
+
+    fn query(&self, sql: &str, params: &[&dyn ToSql]) -> Result<QueryResult, String> {
+        let mut statement = self.conn.prepare(sql).map_err(sql_error)?;
+        let columns: Vec<String> = statement
+            .column_names()
+            .iter()
+            .map(|c| c.to_string())
+            .collect();
+
+        let rows = statement
+            .query_map(params, |row| {
+                (0..columns.len())
+                    .map(|i| {
+                        Ok(match row.get_ref(i)? {
+                            ValueRef::Null => String::new(),
+                            ValueRef::Integer(value) => value.to_string(),
+                            ValueRef::Real(value) => value.to_string(),
+                            ValueRef::Text(value) => String::from_utf8_lossy(value).into_owned(),
+                            ValueRef::Blob(value) => format!("<{} bytes>", value.len()),
+                        })
+                    })
+                    .collect::<Result<Vec<String>, rusqlite::Error>>()
+            })
+            .map_err(sql_error)?
+            .collect::<Result<Vec<Vec<String>>, rusqlite::Error>>()
+            .map_err(sql_error)?;
+
+        Ok(QueryResult { columns, rows })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::detect::test_finding;
+    use crate::report::Summary;
+
+    fn entry(project_id: u64, job_id: u64, created_at: &str) -> ManifestEntry {
+        ManifestEntry {
+            file: format!(
+                "log_traces/group__project-{0}-{0}/{1}.txt",
+                project_id, job_id
+            ),
+            project_id,
+            project_path: format!("group/project-{}", project_id),
+            pipeline_id: 1,
+            job_id,
+            job_name: "build".to_string(),
+            job_status: "success".to_string(),
+            git_ref: "main".to_string(),
+            commit_sha: "abc123".to_string(),
+            job_web_url: String::new(),
+            created_at: created_at.to_string(),
+            started_at: None,
+            finished_at: None,
+            redactions: None,
+        }
+    }
+
+    fn store_with_run() -> Store {
+        let mut store = Store::open(Path::new(":memory:")).unwrap();
+        let mut aws = test_finding(2, 20, 1);
+        aws.rule_id = "aws-access-key".to_string();
+        let report = Report {
+            generated_at: "2025-03-14T10:00:00Z".to_string(),
+            instance_url: "https://gitlab.example.com/api/v4".to_string(),
+            summary: Summary::default(),
+            findings: vec![test_finding(1, 10, 1), aws],
+        };
+        let jobs = vec![
+            entry(1, 10, "2024-11-02T08:00:00Z"),
+            entry(2, 20, "2025-03-01T08:00:00Z"),
+        ];
+
+        store
+            .record_run(&RunRecord {
+                started_at: "2025-03-14T09:00:00Z",
+                run_dir: Path::new("results/20250314T090000Z"),
+                report: &report,
+                groups: &[],
+                projects: &[],
+                jobs: &jobs,
+            })
+            .unwrap();
+        store
+    }
+
+    #[test]
+    fn test_canned_filters() {
+        let store = store_with_run();
+
+        let recent_aws = store
+            .query_findings(&FindingFilter {
+                rule: Some("aws-access-key".to_string()),
+                since: Some("2025-01-01".to_string()),
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!(recent_aws.rows.len(), 1);
+        assert_eq!(recent_aws.rows[0][2], "group/project-2");
+
+        let by_namespace = store
+            .query_findings(&FindingFilter {
+                project: Some("group".to_string()),
+                min_severity: Some(Severity::High),
+                until: Some("2024-11-02".to_string()),
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!(by_namespace.rows.len(), 1);
+        assert_eq!(by_namespace.rows[0][3], "10");
+    }
+
+    #[test]
+    fn test_raw_sql() {
+        let store = store_with_run();
+
+        let result = store
+            .query_sql("SELECT COUNT(*) AS jobs FROM jobs")
+            .unwrap();
+
+        assert_eq!(result.to_tsv(), "jobs\n2");
+    }
+
+    #[test]
+    fn test_namespace_filter_takes_underscores_literally() {
+        let mut store = Store::open(Path::new(":memory:")).unwrap();
+        let mut findings = Vec::new();
+        for (project_id, path) in [
+            (1, "my_group/api"),
+            (2, "myXgroup/api"),
+            (3, "my_group2/api"),
+        ] {
+            let mut finding = test_finding(project_id, project_id * 10, 1);
+            finding.project_path = path.to_string();
+            findings.push(finding);
+        }
+        let report = Report {
+            findings,
+            ..Default::default()
+        };
+        store
+            .record_run(&RunRecord {
+                started_at: "2025-03-14T09:00:00Z",
+                run_dir: Path::new("results/20250314T090000Z"),
+                report: &report,
+                groups: &[],
+                projects: &[],
+                jobs: &[],
+            })
+            .unwrap();
+
+        let result = store
+            .query_findings(&FindingFilter {
+                project: Some("my_group".to_string()),
+                ..Default::default()
+            })
+            .unwrap();
+
+        assert_eq!(result.rows.len(), 1);
+        assert_eq!(result.rows[0][2], "my_group/api");
+    }
+}
@@ -0,0 +1,81 @@
+use futures::future::BoxFuture;
+use reqwest::StatusCode;
+use url::Url;
+
+use crate::detect::Finding;
+use crate::verify::{Provider, RateLimiter, Verification, Verifier, endpoint};
+
+/// Checks npm access tokens with the registry `whoami` endpoint.
+pub struct NpmVerifier {
+    http: reqwest::Client,
+    url: Url,
+}
+
+impl NpmVerifier {
+    pub fn new(http: reqwest::Client, url: Url) -> Self {
+        NpmVerifier { http, url }
+    }
+}
+
+impl Verifier for NpmVerifier {
+    fn provider(&self) -> Provider {
+        Provider::Npm
+    }
+
+    fn applies_to(&self, finding: &Finding) -> bool {
+        finding.secret.starts_with("npm_")
+    }
+
+    fn verify<'a>(
+        &'a self,
+        finding: &'a Finding,
+        _job_findings: &'a [&'a Finding],
+        limiter: &'a RateLimiter,
+    ) -> BoxFuture<'a, Verification> {
+        Box::pin(async move {
+            limiter.wait().await;
+            let response = self
+                .http
+                .get(endpoint(&self.url, "/-/whoami"))
+                .bearer_auth(&finding.secret)
+                .send()
+                .await;
+
+            match response.map(|r| r.status()) {
+                Ok(status) if status.is_success() => Verification::Verified,
+                Ok(StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN) => Verification::Invalid,
+                _ => Verification::Unknown,
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::detect::test_finding;
+    use wiremock::matchers::{header, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    #[tokio::test]
+    async fn test_revoked_token_is_invalid() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/-/whoami"))
+            .and(header("authorization", "Bearer npm_revoked"))
+            .respond_with(ResponseTemplate::new(401))
+            .mount(&server)
+            .await;
+
+        let verifier = NpmVerifier::new(reqwest::Client::new(), Url::parse(&server.uri()).unwrap());
+        let mut finding = test_finding(1, 10, 1);
+        finding.secret = "npm_revoked".to_string();
+
+        assert_eq!(
+            verifier
+                .verify(&finding, &[], &RateLimiter::per_second(100))
+                .await,
+            Verification::Invalid
+        );
+    }
+}